@@ -0,0 +1,132 @@
+//! Chainable construction of CommonMark documents
+
+use super::{DoogieResult, Node, NodeResource};
+use constants::*;
+
+/// A block queued by a `DocumentBuilder`
+enum Block {
+    Heading(u32, String),
+    Paragraph(String),
+    BulletList(Vec<String>),
+    CodeBlock(String, String),
+}
+
+impl Block {
+    /// Creates the subtree of `Node`s for the block
+    fn to_node(&self) -> DoogieResult<Node> {
+        match *self {
+            Block::Heading(level, ref text) => {
+                let mut heading = Node::from_type(NodeType::CMarkNodeHeading)?;
+                if let Node::Heading(ref mut inner) = heading {
+                    inner.set_level(level)?;
+                }
+                heading.append_child(&mut text_node(text)?)?;
+                Ok(heading)
+            }
+            Block::Paragraph(ref text) => paragraph_node(text),
+            Block::BulletList(ref items) => {
+                let mut list = Node::from_type(NodeType::CMarkNodeList)?;
+                if let Node::List(ref mut inner) = list {
+                    inner.set_list_type(ListType::CMarkBulletList)?;
+                    inner.set_tight(true)?;
+                }
+                for text in items {
+                    let mut item = Node::from_type(NodeType::CMarkNodeItem)?;
+                    item.append_child(&mut paragraph_node(text)?)?;
+                    list.append_child(&mut item)?;
+                }
+                Ok(list)
+            }
+            Block::CodeBlock(ref info, ref body) => {
+                let mut block = Node::from_type(NodeType::CMarkNodeCodeBlock)?;
+                if let Node::CodeBlock(ref mut inner) = block {
+                    inner.set_fence_info(info)?;
+                    inner.set_content(body)?;
+                }
+                Ok(block)
+            }
+        }
+    }
+}
+
+/// Creates a `Text` node holding the given content
+fn text_node(content: &str) -> DoogieResult<Node> {
+    let mut text = Node::from_type(NodeType::CMarkNodeText)?;
+    if let Node::Text(ref mut inner) = text {
+        inner.set_content(&content.to_string())?;
+    }
+    Ok(text)
+}
+
+/// Creates a `Paragraph` node holding a single `Text` node with the given content
+fn paragraph_node(content: &str) -> DoogieResult<Node> {
+    let mut paragraph = Node::from_type(NodeType::CMarkNodeParagraph)?;
+    paragraph.append_child(&mut text_node(content)?)?;
+    Ok(paragraph)
+}
+
+/// Builds a `Document` from a sequence of blocks
+///
+/// Blocks are only created when `build` is called, which returns the first error encountered.
+///
+/// # Examples
+///
+/// ```
+/// use doogie::builder::DocumentBuilder;
+///
+/// let root = DocumentBuilder::new()
+///     .heading(1, "Title")
+///     .paragraph("Some text")
+///     .build()
+///     .unwrap();
+///
+/// assert_eq!(root.render_commonmark(), "# Title\n\nSome text\n");
+/// ```
+#[derive(Default)]
+pub struct DocumentBuilder {
+    blocks: Vec<Block>,
+}
+
+impl DocumentBuilder {
+    /// Constructs a new `DocumentBuilder` for an empty document
+    pub fn new() -> Self {
+        DocumentBuilder { blocks: Vec::new() }
+    }
+
+    /// Appends a `Heading` of the given level holding the given text
+    pub fn heading(mut self, level: u32, text: &str) -> Self {
+        self.blocks.push(Block::Heading(level, text.to_string()));
+        self
+    }
+
+    /// Appends a `Paragraph` holding the given text
+    pub fn paragraph(mut self, text: &str) -> Self {
+        self.blocks.push(Block::Paragraph(text.to_string()));
+        self
+    }
+
+    /// Appends a tight bullet `List` with an `Item` holding each of the given texts
+    pub fn bullet_list(mut self, items: Vec<&str>) -> Self {
+        let items = items.iter().map(|item| item.to_string()).collect();
+        self.blocks.push(Block::BulletList(items));
+        self
+    }
+
+    /// Appends a fenced `CodeBlock` with the given info text and content
+    pub fn code_block(mut self, info: &str, body: &str) -> Self {
+        self.blocks.push(Block::CodeBlock(info.to_string(), body.to_string()));
+        self
+    }
+
+    /// Creates the `Document` holding the queued blocks in order
+    pub fn build(self) -> DoogieResult<Node> {
+        let mut root = Node::from_type(NodeType::CMarkNodeDocument)?;
+        root.manager().track_root(&root.pointer());
+
+        for block in &self.blocks {
+            root.append_child(&mut block.to_node()?)?;
+        }
+
+        Ok(root)
+    }
+}
@@ -3,6 +3,7 @@ extern crate try_from;
 use self::try_from::TryFrom;
 use super::{DoogieError, DoogieResult};
 use std::collections::HashSet;
+use std::fmt;
 
 /// Each NodeIterator step is parameterized by one of these event.
 #[derive(PartialEq, Debug)]
@@ -41,6 +42,102 @@ impl TryFrom<u32> for IterEventType {
     }
 }
 
+/// libcmark option including a `data-sourcepos` attribute on block elements
+pub const CMARK_OPT_SOURCEPOS: u32 = 1 << 1;
+
+/// libcmark option rendering soft breaks as hard line breaks
+pub const CMARK_OPT_HARDBREAKS: u32 = 1 << 2;
+
+/// libcmark option rendering soft breaks as spaces
+pub const CMARK_OPT_NOBREAKS: u32 = 1 << 4;
+
+/// libcmark option validating UTF-8 in the input and replacing invalid sequences
+pub const CMARK_OPT_VALIDATE_UTF8: u32 = 1 << 9;
+
+/// libcmark option converting straight quotes to curly, `---` to em dashes and `--` to en dashes
+pub const CMARK_OPT_SMART: u32 = 1 << 10;
+
+/// libcmark option rendering raw HTML and potentially dangerous URLs instead of omitting them
+pub const CMARK_OPT_UNSAFE: u32 = 1 << 17;
+
+/// Options controlling how libcmark parses a document
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct ParseOptions {
+    /// Replace invalid UTF-8 sequences in the input with U+FFFD
+    pub validate_utf8: bool,
+    /// Use smart punctuation
+    pub smart: bool,
+}
+
+impl<'a> From<&'a ParseOptions> for u32 {
+    fn from(original: &'a ParseOptions) -> u32 {
+        let mut options = 0;
+        if original.validate_utf8 {
+            options |= CMARK_OPT_VALIDATE_UTF8;
+        }
+        if original.smart {
+            options |= CMARK_OPT_SMART;
+        }
+        options
+    }
+}
+
+/// Options controlling how libcmark renders a document
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct RenderOptions {
+    /// Include a `data-sourcepos` attribute on block elements
+    pub sourcepos: bool,
+    /// Render soft breaks as hard line breaks
+    pub hardbreaks: bool,
+    /// Render soft breaks as spaces
+    pub nobreaks: bool,
+    /// Render raw HTML and potentially dangerous URLs
+    pub unsafe_html: bool,
+}
+
+impl<'a> From<&'a RenderOptions> for u32 {
+    fn from(original: &'a RenderOptions) -> u32 {
+        let mut options = 0;
+        if original.sourcepos {
+            options |= CMARK_OPT_SOURCEPOS;
+        }
+        if original.hardbreaks {
+            options |= CMARK_OPT_HARDBREAKS;
+        }
+        if original.nobreaks {
+            options |= CMARK_OPT_NOBREAKS;
+        }
+        if original.unsafe_html {
+            options |= CMARK_OPT_UNSAFE;
+        }
+        options
+    }
+}
+
+/// Textual output formats supported by the libcmark renderers
+#[derive(PartialEq, Debug, Clone)]
+pub enum RenderFormat {
+    CommonMark,
+    Xml,
+    Html,
+}
+
+/// Predominant direction of a run of text
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum TextDirection {
+    LeftToRight,
+    RightToLeft,
+}
+
+/// How `Node::fix_empty_link_text` repairs a link without text
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum FallbackText {
+    /// Use the URL of the link as its text
+    UseUrl,
+    /// Unwrap the link, leaving its content in place
+    Remove,
+}
+
 /// Each Node in the libcmark document AST possesses a type attribute that corresponds to its
 /// equivalent CommonMark semantic element.
 #[derive(PartialEq, Debug, Clone, Eq, Hash)]
@@ -127,6 +224,68 @@ impl TryFrom<u32> for NodeType {
     }
 }
 
+/// Parses the type from the string libcmark reports for it, e.g. `block_quote`
+impl<'a> TryFrom<&'a str> for NodeType {
+    type Err = DoogieError;
+
+    fn try_from(original: &'a str) -> DoogieResult<Self> {
+        match original {
+            "none" => Ok(NodeType::CMarkNodeNone),
+            "document" => Ok(NodeType::CMarkNodeDocument),
+            "block_quote" => Ok(NodeType::CMarkNodeBlockQuote),
+            "list" => Ok(NodeType::CMarkNodeList),
+            "item" => Ok(NodeType::CMarkNodeItem),
+            "code_block" => Ok(NodeType::CMarkNodeCodeBlock),
+            "html_block" => Ok(NodeType::CMarkNodeHtmlBlock),
+            "custom_block" => Ok(NodeType::CMarkNodeCustomBlock),
+            "paragraph" => Ok(NodeType::CMarkNodeParagraph),
+            "heading" => Ok(NodeType::CMarkNodeHeading),
+            "thematic_break" => Ok(NodeType::CMarkNodeThematicBreak),
+            "text" => Ok(NodeType::CMarkNodeText),
+            "softbreak" => Ok(NodeType::CMarkNodeSoftbreak),
+            "linebreak" => Ok(NodeType::CMarkNodeLinebreak),
+            "code" => Ok(NodeType::CMarkNodeCode),
+            "html_inline" => Ok(NodeType::CMarkNodeHtmlInline),
+            "custom_inline" => Ok(NodeType::CMarkNodeCustomInline),
+            "emph" => Ok(NodeType::CMarkNodeEmph),
+            "strong" => Ok(NodeType::CMarkNodeStrong),
+            "link" => Ok(NodeType::CMarkNodeLink),
+            "image" => Ok(NodeType::CMarkNodeImage),
+            name => Err(DoogieError::UnknownNodeType(name.to_string())),
+        }
+    }
+}
+
+/// Formats the type as the string libcmark reports for it, e.g. `block_quote`
+impl fmt::Display for NodeType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            NodeType::CMarkNodeNone => "none",
+            NodeType::CMarkNodeDocument => "document",
+            NodeType::CMarkNodeBlockQuote => "block_quote",
+            NodeType::CMarkNodeList => "list",
+            NodeType::CMarkNodeItem => "item",
+            NodeType::CMarkNodeCodeBlock => "code_block",
+            NodeType::CMarkNodeHtmlBlock => "html_block",
+            NodeType::CMarkNodeCustomBlock => "custom_block",
+            NodeType::CMarkNodeParagraph => "paragraph",
+            NodeType::CMarkNodeHeading => "heading",
+            NodeType::CMarkNodeThematicBreak => "thematic_break",
+            NodeType::CMarkNodeText => "text",
+            NodeType::CMarkNodeSoftbreak => "softbreak",
+            NodeType::CMarkNodeLinebreak => "linebreak",
+            NodeType::CMarkNodeCode => "code",
+            NodeType::CMarkNodeHtmlInline => "html_inline",
+            NodeType::CMarkNodeCustomInline => "custom_inline",
+            NodeType::CMarkNodeEmph => "emph",
+            NodeType::CMarkNodeStrong => "strong",
+            NodeType::CMarkNodeLink => "link",
+            NodeType::CMarkNodeImage => "image",
+        };
+        write!(f, "{}", name)
+    }
+}
+
 /// List elements have one of these types associated with them
 #[derive(PartialEq)]
 pub enum ListType {
@@ -338,3 +497,53 @@ lazy_static! {
 lazy_static! {
     pub static ref IMAGE_CHILDREN: HashSet<NodeType> = { PARAGRAPH_CHILDREN.clone() };
 }
+
+/// Node types that represent CommonMark block elements
+lazy_static! {
+    pub static ref BLOCK_TYPES: HashSet<NodeType> = {
+        let mut types = HashSet::new();
+        types.insert(NodeType::CMarkNodeDocument);
+        types.insert(NodeType::CMarkNodeBlockQuote);
+        types.insert(NodeType::CMarkNodeList);
+        types.insert(NodeType::CMarkNodeItem);
+        types.insert(NodeType::CMarkNodeCodeBlock);
+        types.insert(NodeType::CMarkNodeHtmlBlock);
+        types.insert(NodeType::CMarkNodeCustomBlock);
+        types.insert(NodeType::CMarkNodeParagraph);
+        types.insert(NodeType::CMarkNodeHeading);
+        types.insert(NodeType::CMarkNodeThematicBreak);
+        types
+    };
+}
+
+/// Node types that represent CommonMark inline elements
+lazy_static! {
+    pub static ref INLINE_TYPES: HashSet<NodeType> = {
+        let mut types = HashSet::new();
+        types.insert(NodeType::CMarkNodeText);
+        types.insert(NodeType::CMarkNodeSoftbreak);
+        types.insert(NodeType::CMarkNodeLinebreak);
+        types.insert(NodeType::CMarkNodeCode);
+        types.insert(NodeType::CMarkNodeHtmlInline);
+        types.insert(NodeType::CMarkNodeCustomInline);
+        types.insert(NodeType::CMarkNodeEmph);
+        types.insert(NodeType::CMarkNodeStrong);
+        types.insert(NodeType::CMarkNodeLink);
+        types.insert(NodeType::CMarkNodeImage);
+        types
+    };
+}
+
+/// Common abbreviations, lowercased and without their final period, that do not end a sentence
+lazy_static! {
+    pub static ref ABBREVIATIONS: HashSet<&'static str> = {
+        let mut abbreviations = HashSet::new();
+        for abbreviation in &[
+            "mr", "mrs", "ms", "dr", "prof", "sr", "jr", "st", "vs", "etc", "e.g", "i.e", "inc",
+            "ltd", "co", "no", "fig", "approx", "cf", "al",
+        ] {
+            abbreviations.insert(*abbreviation);
+        }
+        abbreviations
+    };
+}
@@ -1,3 +1,4 @@
+use constants::NodeType;
 use std::error;
 use std::ffi::NulError;
 use std::fmt;
@@ -11,10 +12,28 @@ pub enum DoogieError {
     Utf8Error(Utf8Error),
     ReturnCode(u32),
     BadEnum(u32),
+    InvalidHeadingLevel(u32),
     IOError(IOError),
     ResourceUnavailable,
     NodeNone,
     FmtError(fmt::Error),
+    InvalidUtf8 {
+        line: u32,
+        column: u32,
+        err: Utf8Error,
+    },
+    InvalidOffset(usize),
+    UnsupportedNodeType(NodeType),
+    UnknownNodeType(String),
+    InvalidChild {
+        parent: NodeType,
+        child: NodeType,
+    },
+    CyclicAppend,
+    NoParent,
+    InvalidContent(NodeType),
+    InvalidFenceInfo(char),
+    UnknownListAttribute(String),
 }
 
 impl fmt::Display for DoogieError {
@@ -25,11 +44,37 @@ impl fmt::Display for DoogieError {
             DoogieError::IOError(ref err) => write!(f, "IOError: {}", err),
             DoogieError::ReturnCode(code) => write!(f, "CMark return code: {}", code),
             DoogieError::BadEnum(num) => write!(f, "Bad Enum Value: {}", num),
+            DoogieError::InvalidHeadingLevel(level) => write!(f, "Invalid Heading Level: {}", level),
             DoogieError::ResourceUnavailable => write!(f, "The resource is no longer available"),
             DoogieError::NodeNone => {
                 write!(f, "CMark has erroneously returned null for this operation")
             }
             DoogieError::FmtError(ref err) => write!(f, "FmtError: {}", err),
+            DoogieError::InvalidUtf8 {
+                line,
+                column,
+                ref err,
+            } => write!(f, "Utf8Error at line {}, column {}: {}", line, column, err),
+            DoogieError::InvalidOffset(offset) => write!(f, "Invalid Offset: {}", offset),
+            DoogieError::UnsupportedNodeType(ref node_type) => {
+                write!(f, "Unsupported Node Type: {:?}", node_type)
+            }
+            DoogieError::UnknownNodeType(ref name) => write!(f, "Unknown Node Type: {}", name),
+            DoogieError::InvalidChild {
+                ref parent,
+                ref child,
+            } => write!(f, "Invalid Child: {} cannot contain {}", parent, child),
+            DoogieError::CyclicAppend => write!(f, "Cyclic Append: a node cannot contain itself"),
+            DoogieError::NoParent => write!(f, "No Parent: the node is not part of a tree"),
+            DoogieError::InvalidContent(ref node_type) => {
+                write!(f, "Invalid Content: {} content cannot contain newlines", node_type)
+            }
+            DoogieError::InvalidFenceInfo(c) => {
+                write!(f, "Invalid Fence Info: info text cannot contain {:?}", c)
+            }
+            DoogieError::UnknownListAttribute(ref name) => {
+                write!(f, "Unknown List Attribute: {}", name)
+            }
         }
     }
 }
@@ -42,9 +87,32 @@ impl error::Error for DoogieError {
             DoogieError::IOError(ref err) => err.description(),
             DoogieError::ReturnCode(_code) => "libcmark returned an error code.",
             DoogieError::BadEnum(_num) => "libcmark returned an invalid node type.",
+            DoogieError::InvalidHeadingLevel(_level) => "Heading levels must be between 1 and 6.",
             DoogieError::ResourceUnavailable => "The resource is no longer available.",
             DoogieError::NodeNone => "libcmark returned Node::None which is an error.",
             DoogieError::FmtError(ref err) => err.description(),
+            DoogieError::InvalidUtf8 { ref err, .. } => err.description(),
+            DoogieError::InvalidOffset(_offset) => {
+                "Offsets must fall on a character boundary within the content."
+            }
+            DoogieError::UnsupportedNodeType(_) => {
+                "The operation is not supported for this node type."
+            }
+            DoogieError::UnknownNodeType(_) => "The name is not a libcmark node type.",
+            DoogieError::InvalidChild { .. } => {
+                "The node cannot be a child of a node of the parent type."
+            }
+            DoogieError::CyclicAppend => "A node cannot be moved into its own subtree.",
+            DoogieError::NoParent => "The operation requires the node to have a parent.",
+            DoogieError::InvalidContent(_) => {
+                "The content of inline nodes cannot contain newlines."
+            }
+            DoogieError::InvalidFenceInfo(_) => {
+                "The info text of code blocks cannot contain backticks or line breaks."
+            }
+            DoogieError::UnknownListAttribute(_) => {
+                "The name is not a list type or delimiter type."
+            }
         }
     }
 
@@ -55,9 +123,20 @@ impl error::Error for DoogieError {
             DoogieError::IOError(ref err) => Some(err),
             DoogieError::ReturnCode(_code) => None,
             DoogieError::BadEnum(_num) => None,
+            DoogieError::InvalidHeadingLevel(_level) => None,
             DoogieError::ResourceUnavailable => None,
             DoogieError::NodeNone => None,
             DoogieError::FmtError(ref err) => Some(err),
+            DoogieError::InvalidUtf8 { ref err, .. } => Some(err),
+            DoogieError::InvalidOffset(_offset) => None,
+            DoogieError::UnsupportedNodeType(_) => None,
+            DoogieError::UnknownNodeType(_) => None,
+            DoogieError::InvalidChild { .. } => None,
+            DoogieError::CyclicAppend => None,
+            DoogieError::NoParent => None,
+            DoogieError::InvalidContent(_) => None,
+            DoogieError::InvalidFenceInfo(_) => None,
+            DoogieError::UnknownListAttribute(_) => None,
         }
     }
 }
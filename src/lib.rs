@@ -1,5 +1,3 @@
-#![feature(vec_remove_item)]
-
 #[cfg(test)]
 #[macro_use]
 extern crate proptest;
@@ -7,22 +5,34 @@ extern crate proptest;
 extern crate log;
 #[macro_use]
 extern crate lazy_static;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(all(test, feature = "serde"))]
+extern crate serde_json;
 
 extern crate env_logger;
 extern crate libc;
 extern crate try_from;
 
+pub mod builder;
 pub mod constants;
 pub mod errors;
+#[cfg(feature = "serde")]
+pub mod serialization;
 
-use self::libc::{c_char, c_int, c_void, size_t};
+use self::libc::{c_char, c_int, c_void, free, size_t};
 use self::try_from::TryFrom;
 use constants::*;
 use errors::DoogieError;
 use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
 use std::ffi::CStr;
 use std::ffi::CString;
-use std::fmt::{Debug, Error, Formatter};
+use std::fmt::{Debug, Display, Error, Formatter};
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::ptr;
 use std::rc::Rc;
 
 /// Result type for the Doogie crate
@@ -32,12 +42,22 @@ pub type DoogieResult<T> = Result<T, DoogieError>;
 pub enum CMarkNodePtr {}
 /// Represents libcmark iterator pointers as an opaque struct
 enum CMarkIterPtr {}
+/// Represents libcmark parser pointers as an opaque struct
+enum CMarkParserPtr {}
 
 extern "C" {
     fn cmark_node_new(node_type: u32) -> *mut CMarkNodePtr;
 
     fn cmark_parse_document(buffer: *const u8, len: size_t, options: c_int) -> *mut CMarkNodePtr;
 
+    fn cmark_parser_new(options: c_int) -> *mut CMarkParserPtr;
+
+    fn cmark_parser_feed(parser: *mut CMarkParserPtr, buffer: *const u8, len: size_t);
+
+    fn cmark_parser_finish(parser: *mut CMarkParserPtr) -> *mut CMarkNodePtr;
+
+    fn cmark_parser_free(parser: *mut CMarkParserPtr);
+
     fn cmark_node_free(node: *mut CMarkNodePtr);
 
     fn cmark_node_get_literal(node: *mut CMarkNodePtr) -> *const c_char;
@@ -52,20 +72,50 @@ extern "C" {
 
     fn cmark_node_get_start_column(node: *mut CMarkNodePtr) -> c_int;
 
+    fn cmark_node_get_end_line(node: *mut CMarkNodePtr) -> c_int;
+
+    fn cmark_node_get_end_column(node: *mut CMarkNodePtr) -> c_int;
+
     fn cmark_node_get_list_type(node: *mut CMarkNodePtr) -> c_int;
 
     fn cmark_node_get_list_delim(node: *mut CMarkNodePtr) -> c_int;
 
+    fn cmark_node_set_list_type(node: *mut CMarkNodePtr, list_type: c_int) -> c_int;
+
+    fn cmark_node_set_list_delim(node: *mut CMarkNodePtr, delim: c_int) -> c_int;
+
+    fn cmark_node_get_list_start(node: *mut CMarkNodePtr) -> c_int;
+
+    fn cmark_node_set_list_start(node: *mut CMarkNodePtr, start: c_int) -> c_int;
+
+    fn cmark_node_get_list_tight(node: *mut CMarkNodePtr) -> c_int;
+
+    fn cmark_node_set_list_tight(node: *mut CMarkNodePtr, tight: c_int) -> c_int;
+
     fn cmark_node_get_heading_level(node: *mut CMarkNodePtr) -> c_int;
 
+    fn cmark_node_set_heading_level(node: *mut CMarkNodePtr, level: c_int) -> c_int;
+
     fn cmark_node_get_url(node: *mut CMarkNodePtr) -> *const c_char;
 
+    fn cmark_node_set_url(node: *mut CMarkNodePtr, url: *const c_char) -> c_int;
+
     fn cmark_node_get_title(node: *mut CMarkNodePtr) -> *const c_char;
 
+    fn cmark_node_set_title(node: *mut CMarkNodePtr, title: *const c_char) -> c_int;
+
     fn cmark_node_get_fence_info(node: *mut CMarkNodePtr) -> *const c_char;
 
     fn cmark_node_set_fence_info(node: *mut CMarkNodePtr, info: *const c_char) -> c_int;
 
+    fn cmark_node_get_on_enter(node: *mut CMarkNodePtr) -> *const c_char;
+
+    fn cmark_node_set_on_enter(node: *mut CMarkNodePtr, on_enter: *const c_char) -> c_int;
+
+    fn cmark_node_get_on_exit(node: *mut CMarkNodePtr) -> *const c_char;
+
+    fn cmark_node_set_on_exit(node: *mut CMarkNodePtr, on_exit: *const c_char) -> c_int;
+
     fn cmark_node_next(node: *mut CMarkNodePtr) -> *mut CMarkNodePtr;
 
     fn cmark_node_previous(node: *mut CMarkNodePtr) -> *mut CMarkNodePtr;
@@ -80,12 +130,26 @@ extern "C" {
 
     fn cmark_node_append_child(node: *mut CMarkNodePtr, child: *mut CMarkNodePtr) -> c_int;
 
+    fn cmark_node_prepend_child(node: *mut CMarkNodePtr, child: *mut CMarkNodePtr) -> c_int;
+
+    fn cmark_node_insert_before(node: *mut CMarkNodePtr, sibling: *mut CMarkNodePtr) -> c_int;
+
+    fn cmark_node_insert_after(node: *mut CMarkNodePtr, sibling: *mut CMarkNodePtr) -> c_int;
+
+    fn cmark_node_replace(oldnode: *mut CMarkNodePtr, newnode: *mut CMarkNodePtr) -> c_int;
+
     fn cmark_consolidate_text_nodes(root: *mut CMarkNodePtr) -> c_void;
 
     fn cmark_render_xml(root: *mut CMarkNodePtr, options: c_int) -> *const c_char;
 
     fn cmark_render_commonmark(root: *mut CMarkNodePtr, options: c_int) -> *const c_char;
 
+    fn cmark_render_html(root: *mut CMarkNodePtr, options: c_int) -> *const c_char;
+
+    fn cmark_render_latex(root: *mut CMarkNodePtr, options: c_int, width: c_int) -> *const c_char;
+
+    fn cmark_render_man(root: *mut CMarkNodePtr, options: c_int, width: c_int) -> *const c_char;
+
     fn cmark_iter_new(node: *mut CMarkNodePtr) -> *mut CMarkIterPtr;
 
     fn cmark_iter_get_node(iter: *mut CMarkIterPtr) -> *mut CMarkNodePtr;
@@ -133,21 +197,328 @@ impl Resource {
 /// let root = parse_document(document);
 /// ```
 pub fn parse_document(buffer: &str) -> Node {
-    let buffer = buffer.as_bytes();
+    parse_bytes(buffer.as_bytes())
+}
+
+/// Parses the text of a CommonMark document and returns the root node of the document tree, or an
+/// error if libcmark does not produce a valid `Document` root.
+///
+/// Returns `DoogieError::NodeNone` when libcmark returns no root or a root of another type.
+pub fn parse_document_checked(buffer: &str) -> DoogieResult<Node> {
+    parse_bytes_with_options(buffer.as_bytes(), &ParseOptions::default())
+}
+
+/// Parses a CommonMark document with the given options and returns the root node of the document
+/// tree, recording the bullet marker of each bullet list in the source
+///
+/// The markers are restored by `Node::render_commonmark_preserving_markers`. Recording them takes
+/// an extra pass over the tree, which the other parse functions skip.
+pub fn parse_document_preserving_markers(
+    buffer: &str,
+    options: &ParseOptions,
+) -> DoogieResult<Node> {
+    let root = parse_bytes_with_options(buffer.as_bytes(), options)?;
+    record_list_markers(root.pointer(), buffer.as_bytes())?;
+
+    Ok(root)
+}
+
+/// Parses a CommonMark document with the given options and returns the root node of the document
+/// tree.
+pub fn parse_document_with_options(buffer: &str, options: &ParseOptions) -> Node {
+    parse_bytes_with_options(buffer.as_bytes(), options)
+        .expect("libcmark did not produce a Document root")
+}
+
+/// Parses the raw bytes of a CommonMark document and returns the root node of the document tree.
+///
+/// The bytes are not validated as UTF-8, so the accessors of nodes holding invalid content will
+/// return errors. Use `Document::validate_content_utf8` to check the whole tree up front.
+pub fn parse_bytes(buffer: &[u8]) -> Node {
+    parse_bytes_with_options(buffer, &ParseOptions::default())
+        .expect("libcmark did not produce a Document root")
+}
+
+/// Reads a CommonMark document to the end of the given reader and returns the root node of the
+/// document tree
+///
+/// As with `parse_bytes`, the content is not validated as UTF-8. Errors reading the stream are
+/// returned as `DoogieError::IOError`.
+pub fn parse_reader<R: Read>(reader: &mut R) -> DoogieResult<Node> {
+    let mut buffer = Vec::new();
+    reader.read_to_end(&mut buffer)?;
+
+    parse_bytes_with_options(&buffer, &ParseOptions::default())
+}
+
+/// Reads the CommonMark document at the given path and returns the root node of the document tree
+///
+/// An empty file yields an empty `Document`. Errors opening or reading the file are returned as
+/// `DoogieError::IOError`.
+pub fn parse_file<P: AsRef<Path>>(path: P) -> DoogieResult<Node> {
+    parse_reader(&mut File::open(path)?)
+}
+
+/// Parses a CommonMark document and returns the root node of the document tree, after splitting
+/// off any YAML-style front matter
+///
+/// Front matter is a block at the very start of the buffer opened by a `---` line and closed by a
+/// `---` or `...` line. Its raw text, without the delimiter lines, is returned alongside the
+/// parsed remainder of the buffer. Without a complete front matter block the whole buffer is
+/// parsed and `None` is returned. Source positions in the tree are relative to the remainder.
+///
+/// The line after the opening `---` must not be blank, so a document starting with a thematic
+/// break is not mistaken for front matter.
+pub fn parse_with_front_matter(
+    buffer: &str,
+    opts: &ParseOptions,
+) -> DoogieResult<(Option<String>, Node)> {
+    let (front_matter, body) = split_front_matter(buffer);
+
+    Ok((
+        front_matter.map(|text| text.to_string()),
+        parse_bytes_with_options(body.as_bytes(), opts)?,
+    ))
+}
+
+/// Parses a CommonMark document and returns its plain textual content, as given by
+/// `Node::to_plain_text`
+///
+/// The tree is walked with a libcmark iterator without creating a `Node` for each node, and the
+/// literals are copied straight into the returned `String`. The document tree is freed before
+/// returning.
+pub fn extract_plain_text(buffer: &str, opts: &ParseOptions) -> DoogieResult<String> {
+    let root_ptr: *mut CMarkNodePtr;
+    unsafe {
+        root_ptr = cmark_parse_document(
+            buffer.as_ptr(),
+            buffer.len() as size_t,
+            u32::from(opts) as c_int,
+        );
+    }
+    if root_ptr.is_null() {
+        return Err(DoogieError::NodeNone);
+    }
+
+    let mut text = String::with_capacity(buffer.len());
+    let result = walk_raw(root_ptr, |pointer, event| {
+        let node_type;
+        unsafe {
+            node_type = NodeType::try_from(cmark_node_get_type(pointer) as u32)?;
+        }
+
+        match (node_type, event) {
+            (NodeType::CMarkNodeText, IterEventType::Enter)
+            | (NodeType::CMarkNodeCode, IterEventType::Enter) => {
+                push_literal(&mut text, pointer)?;
+            }
+            (NodeType::CMarkNodeCodeBlock, IterEventType::Enter) => {
+                if !push_literal(&mut text, pointer)? {
+                    text.push('\n');
+                }
+            }
+            (NodeType::CMarkNodeSoftbreak, IterEventType::Enter)
+            | (NodeType::CMarkNodeLinebreak, IterEventType::Enter)
+            | (NodeType::CMarkNodeParagraph, IterEventType::Exit)
+            | (NodeType::CMarkNodeHeading, IterEventType::Exit) => text.push('\n'),
+            _ => (),
+        }
+
+        Ok(())
+    });
+
+    unsafe {
+        cmark_node_free(root_ptr);
+    }
+    result?;
+
+    Ok(text)
+}
+
+/// Appends the literal content of the given node pointer, if it has any, to the given text
+///
+/// Returns whether the appended content ends with a newline.
+fn push_literal(text: &mut String, pointer: *mut CMarkNodePtr) -> DoogieResult<bool> {
+    unsafe {
+        let literal = cmark_node_get_literal(pointer);
+        if literal.is_null() {
+            return Ok(false);
+        }
+
+        let literal = CStr::from_ptr(literal).to_str()?;
+        text.push_str(literal);
+        Ok(literal.ends_with('\n'))
+    }
+}
+
+/// Parses the raw bytes of a CommonMark document with the given options, checking that libcmark
+/// produced a `Document` root
+fn parse_bytes_with_options(buffer: &[u8], options: &ParseOptions) -> DoogieResult<Node> {
     let buffer_len = buffer.len() as size_t;
     let p_buffer = buffer.as_ptr();
-    let manager = Rc::new(ResourceManager::new());
     let root_ptr: *mut CMarkNodePtr;
     unsafe {
-        root_ptr = cmark_parse_document(p_buffer, buffer_len, 0);
+        root_ptr = cmark_parse_document(p_buffer, buffer_len, u32::from(options) as c_int);
     }
+
+    document_from_parsed_root(root_ptr)
+}
+
+/// Wraps the root returned by libcmark for a parsed document in a `Node` owning the tree
+///
+/// Returns `DoogieError::NodeNone` when the root is null or not a `Document`.
+fn document_from_parsed_root(root_ptr: *mut CMarkNodePtr) -> DoogieResult<Node> {
+    if root_ptr.is_null() {
+        return Err(DoogieError::NodeNone);
+    }
+
+    let manager = Rc::new(ResourceManager::new());
     manager.track_root(&root_ptr);
 
-    Node::Document(Document {
+    let root_type;
+    unsafe {
+        root_type = NodeType::try_from(cmark_node_get_type(root_ptr) as u32);
+    }
+    if root_type.ok() != Some(NodeType::CMarkNodeDocument) {
+        return Err(DoogieError::NodeNone);
+    }
+
+    Ok(Node::Document(Document {
         resource: Resource {
             pointer: root_ptr,
             manager,
         },
+    }))
+}
+
+/// Converts a string returned by a libcmark getter into an owned `String`, treating null as empty
+fn literal_to_string(literal: *const c_char) -> DoogieResult<String> {
+    if literal.is_null() {
+        Ok(String::new())
+    } else {
+        unsafe { Ok(CStr::from_ptr(literal).to_str()?.to_string()) }
+    }
+}
+
+thread_local! {
+    /// Bullet markers recorded by `parse_document_preserving_markers`, keyed by `List` pointer
+    ///
+    /// Entries are removed by `forget_list_markers` before the nodes are freed, so a pointer
+    /// reused by a later allocation never inherits a marker.
+    static LIST_MARKERS: RefCell<HashMap<*mut CMarkNodePtr, char>> = RefCell::new(HashMap::new());
+}
+
+/// Calls the given function with each node pointer and event of the subtree rooted at the given
+/// pointer, driving a libcmark iterator directly instead of wrapping each node in a `Node`
+fn walk_raw<F>(root: *mut CMarkNodePtr, mut visit: F) -> DoogieResult<()>
+where
+    F: FnMut(*mut CMarkNodePtr, IterEventType) -> DoogieResult<()>,
+{
+    let iter;
+    unsafe {
+        iter = cmark_iter_new(root);
+    }
+    if iter.is_null() {
+        return Err(DoogieError::NodeNone);
+    }
+
+    let mut result = Ok(());
+    loop {
+        let event;
+        unsafe {
+            event = IterEventType::try_from(cmark_iter_next(iter) as u32);
+        }
+
+        match event {
+            Ok(IterEventType::Done) | Ok(IterEventType::None) => break,
+            Ok(event) => {
+                let pointer;
+                unsafe {
+                    pointer = cmark_iter_get_node(iter);
+                }
+                result = visit(pointer, event);
+            }
+            Err(err) => result = Err(err),
+        }
+
+        if result.is_err() {
+            break;
+        }
+    }
+
+    unsafe {
+        cmark_iter_free(iter);
+    }
+    result
+}
+
+/// Records the bullet marker character of every bullet `List` in the tree from the source text
+fn record_list_markers(root: *mut CMarkNodePtr, source: &[u8]) -> DoogieResult<()> {
+    let lines: Vec<&[u8]> = source.split(|&byte| byte == b'\n').collect();
+
+    LIST_MARKERS.with(|markers| {
+        let mut markers = markers.borrow_mut();
+
+        walk_raw(root, |pointer, event| {
+            if event != IterEventType::Enter {
+                return Ok(());
+            }
+
+            let (node_type, line, column);
+            unsafe {
+                node_type = NodeType::try_from(cmark_node_get_type(pointer) as u32)?;
+                line = cmark_node_get_start_line(pointer) as usize;
+                column = cmark_node_get_start_column(pointer) as usize;
+            }
+            if node_type != NodeType::CMarkNodeList || line == 0 || column == 0 {
+                return Ok(());
+            }
+            unsafe {
+                if ListType::try_from(cmark_node_get_list_type(pointer) as u32)?
+                    != ListType::CMarkBulletList
+                {
+                    return Ok(());
+                }
+            }
+
+            let marker = lines
+                .get(line - 1)
+                .and_then(|text| text.get(column - 1))
+                .cloned();
+            match marker {
+                Some(marker @ b'-') | Some(marker @ b'+') | Some(marker @ b'*') => {
+                    markers.insert(pointer, marker as char);
+                }
+                _ => (),
+            }
+
+            Ok(())
+        })
+    })
+}
+
+/// Returns the bullet marker recorded for the given `List` pointer by `record_list_markers`, if
+/// any
+fn recorded_list_marker(pointer: *mut CMarkNodePtr) -> Option<char> {
+    LIST_MARKERS.with(|markers| markers.borrow().get(&pointer).cloned())
+}
+
+/// Removes the recorded bullet markers of the subtree rooted at the given pointer, which is about
+/// to be freed or handed over to C code
+fn forget_list_markers(root: *mut CMarkNodePtr) {
+    LIST_MARKERS.with(|markers| {
+        let mut markers = markers.borrow_mut();
+        if markers.is_empty() {
+            return;
+        }
+
+        let result = walk_raw(root, |pointer, _| {
+            markers.remove(&pointer);
+            Ok(())
+        });
+        if let Err(err) = result {
+            warn!("Could not forget the list markers of a subtree: {}", err);
+        }
     })
 }
 
@@ -254,6 +625,13 @@ impl Debug for Node {
     }
 }
 
+impl Display for Node {
+    /// Writes the subtree rooted at the `Node` in textual CommonMark form
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(f, "{}", self.render_commonmark())
+    }
+}
+
 impl Node {
     /// Construct a Rust Node wrapper around a pointer to a libcmark node
     fn from_raw(pointer: *mut CMarkNodePtr) -> DoogieResult<Self> {
@@ -303,12 +681,34 @@ impl Node {
     }
 
     /// Returns the Rust equivalent of a libcmark NodeType enum
+    ///
+    /// The type of a libcmark node never changes, so it is read once when the `Node` is created
+    /// and given by its variant afterwards, without calling into libcmark.
     pub fn get_cmark_type(&self) -> DoogieResult<NodeType> {
-        let t: i32;
-        unsafe {
-            t = cmark_node_get_type(self.pointer());
-        }
-        Ok(NodeType::try_from(t as u32)?)
+        let node_type = match *self {
+            Node::Document(_) => NodeType::CMarkNodeDocument,
+            Node::BlockQuote(_) => NodeType::CMarkNodeBlockQuote,
+            Node::List(_) => NodeType::CMarkNodeList,
+            Node::Item(_) => NodeType::CMarkNodeItem,
+            Node::CodeBlock(_) => NodeType::CMarkNodeCodeBlock,
+            Node::HtmlBlock(_) => NodeType::CMarkNodeHtmlBlock,
+            Node::CustomBlock(_) => NodeType::CMarkNodeCustomBlock,
+            Node::Paragraph(_) => NodeType::CMarkNodeParagraph,
+            Node::Heading(_) => NodeType::CMarkNodeHeading,
+            Node::ThematicBreak(_) => NodeType::CMarkNodeThematicBreak,
+            Node::Text(_) => NodeType::CMarkNodeText,
+            Node::SoftBreak(_) => NodeType::CMarkNodeSoftbreak,
+            Node::LineBreak(_) => NodeType::CMarkNodeLinebreak,
+            Node::Code(_) => NodeType::CMarkNodeCode,
+            Node::HtmlInline(_) => NodeType::CMarkNodeHtmlInline,
+            Node::CustomInline(_) => NodeType::CMarkNodeCustomInline,
+            Node::Emph(_) => NodeType::CMarkNodeEmph,
+            Node::Strong(_) => NodeType::CMarkNodeStrong,
+            Node::Link(_) => NodeType::CMarkNodeLink,
+            Node::Image(_) => NodeType::CMarkNodeImage,
+        };
+
+        Ok(node_type)
     }
 
     /// Returns a unique numerical identity for the `Node`
@@ -373,6 +773,18 @@ impl Node {
         }
     }
 
+    /// Returns the number of ancestors of the current `Node`, zero for the root of a tree
+    pub fn depth(&self) -> DoogieResult<usize> {
+        let mut depth = 0;
+        let mut ancestor = self.parent()?;
+        while let Some(parent) = ancestor {
+            depth += 1;
+            ancestor = parent.parent()?;
+        }
+
+        Ok(depth)
+    }
+
     /// Returns the first child Node of the current `Node` if it exists
     pub fn first_child(&self) -> DoogieResult<Option<Node>> {
         let child_ptr: *mut CMarkNodePtr;
@@ -401,6 +813,45 @@ impl Node {
         }
     }
 
+    /// Releases the libcmark node pointer of the current `Node` to the caller, e.g. to hand it to
+    /// C code
+    ///
+    /// If the `Node` owns its tree, as the root returned by the parse functions or a detached
+    /// `Node` does, the caller becomes responsible for freeing it with `cmark_node_free` or for
+    /// passing it back to `Node::from_raw_owned`. A `Node` still linked into a tree remains owned
+    /// by that tree and its pointer is only valid for as long as the tree lives.
+    pub fn into_raw(self) -> *mut CMarkNodePtr {
+        let pointer = self.pointer();
+        let parent;
+        unsafe {
+            parent = cmark_node_parent(pointer);
+        }
+        if parent.is_null() {
+            forget_list_markers(pointer);
+        }
+        self.manager().untrack_root(&pointer);
+        pointer
+    }
+
+    /// Takes ownership of a libcmark node pointer, such as one released by `Node::into_raw`,
+    /// freeing the node and its subtree when the returned `Node` is dropped
+    ///
+    /// Returns `DoogieError::NodeNone` for a null pointer.
+    ///
+    /// # Safety
+    ///
+    /// The pointer must point to a valid libcmark node without a parent that is not owned
+    /// elsewhere, or it may be freed twice.
+    pub unsafe fn from_raw_owned(pointer: *mut CMarkNodePtr) -> DoogieResult<Node> {
+        if pointer.is_null() {
+            return Err(DoogieError::NodeNone);
+        }
+
+        let node = Node::from_raw(pointer)?;
+        node.manager().track_root(&pointer);
+        Ok(node)
+    }
+
     /// Returns a new instance of the current `Node`
     ///
     /// The returned `Node` will share the underlying memory resource and manager of the current Node.
@@ -419,13 +870,59 @@ impl Node {
         self.manager().track_root(&self.pointer());
     }
 
+    /// Unlinks the current `Node` from its position in the document AST and returns it as the owned
+    /// root of its subtree
+    ///
+    /// The returned `Node` keeps the subtree alive after the document it was unlinked from is
+    /// dropped, so `Node`s previously obtained for its descendants remain valid for as long as it
+    /// lives. It can be reattached elsewhere with `append_child` and similar methods.
+    pub fn detach(mut self) -> Node {
+        self.unlink();
+        self
+    }
+
+    /// Moves the current `Node` into a new `Document`, returning the root of the new document
+    ///
+    /// Block nodes become children of the document, inline nodes are wrapped in a `Paragraph` and
+    /// an `Item` in a `List`. Returns `DoogieError::UnsupportedNodeType` for a `Document`. This
+    /// allows rendering a fragment of a tree on its own.
+    pub fn wrap_in_document(&self) -> DoogieResult<Node> {
+        let node_type = self.get_cmark_type()?;
+        let mut container = if DOCUMENT_CHILDREN.contains(&node_type) {
+            None
+        } else if PARAGRAPH_CHILDREN.contains(&node_type) {
+            Some(Node::from_type(NodeType::CMarkNodeParagraph)?)
+        } else if node_type == NodeType::CMarkNodeItem {
+            Some(Node::from_type(NodeType::CMarkNodeList)?)
+        } else {
+            return Err(DoogieError::UnsupportedNodeType(node_type));
+        };
+
+        let mut root = Node::from_type(NodeType::CMarkNodeDocument)?;
+        root.manager().track_root(&root.pointer());
+
+        let mut node = self.itself()?;
+        match container {
+            Some(ref mut container) => {
+                container.append_child(&mut node)?;
+                root.append_child(container)?;
+            }
+            None => root.append_child(&mut node)?,
+        }
+        self.manager().untrack_root(&self.pointer());
+
+        Ok(root)
+    }
+
     /// Append the given `Node` as the last child of the current `Node` if possible
     ///
     /// The rules of the CommonMark AST must be respected when appending nodes. Not all Nodes can
     /// be appended to each particular type of Node. Use `can_append_child` to determine if the
-    /// operation will succeed. An error will be returned along with the libcmark error code if the
-    /// operation.
+    /// operation will succeed. A `DoogieError::InvalidChild` error is returned, leaving the given
+    /// `Node` untouched, if it is not a valid child. An error will be returned along with the
+    /// libcmark error code if the operation otherwise fails.
     pub fn append_child(&mut self, child: &mut Node) -> DoogieResult<()> {
+        self.check_child(child)?;
         child.unlink();
         let result: i32;
         unsafe {
@@ -441,1593 +938,6937 @@ impl Node {
         }
     }
 
-    /// Determines if the given `Node` is a potentially valid child of the current `Node`
-    pub fn can_append_child(&self, child: &Node) -> DoogieResult<bool> {
-        let child_type = child.get_cmark_type()?;
-
-        let result = match self {
-            Node::Document(_) => DOCUMENT_CHILDREN.contains(&child_type),
-            Node::BlockQuote(_) => BLOCK_QUOTE_CHILDREN.contains(&child_type),
-            Node::List(_) => child_type == NodeType::CMarkNodeItem,
-            Node::Item(_) => ITEM_CHILDREN.contains(&child_type),
-            Node::CodeBlock(_) => CODE_BLOCK_CHILDREN.contains(&child_type),
-            Node::HtmlBlock(_) => HTML_BLOCK_CHILDREN.contains(&child_type),
-            Node::CustomBlock(_) => CUSTOM_BLOCK_CHILDREN.contains(&child_type),
-            Node::Paragraph(_) => PARAGRAPH_CHILDREN.contains(&child_type),
-            Node::Heading(_) => HEADING_CHILDREN.contains(&child_type),
-            Node::ThematicBreak(_) => THEMATIC_BREAK_CHILDREN.contains(&child_type),
-            Node::Text(_) => TEXT_CHILDREN.contains(&child_type),
-            Node::SoftBreak(_) => SOFT_BREAK_CHILDREN.contains(&child_type),
-            Node::LineBreak(_) => LINE_BREAK_CHILDREN.contains(&child_type),
-            Node::Code(_) => CODE_CHILDREN.contains(&child_type),
-            Node::HtmlInline(_) => INLINE_HTML_CHILDREN.contains(&child_type),
-            Node::CustomInline(_) => CUSTOM_INLINE_CHILDREN.contains(&child_type),
-            Node::Emph(_) => EMPH_CHILDREN.contains(&child_type),
-            Node::Strong(_) => STRONG_CHILDREN.contains(&child_type),
-            Node::Link(_) => LINK_CHILDREN.contains(&child_type),
-            Node::Image(_) => IMAGE_CHILDREN.contains(&child_type),
-        };
+    /// Prepend the given `Node` as the first child of the current `Node` if possible
+    ///
+    /// The given `Node` is checked with `can_append_child` and left untouched if it is not a valid
+    /// child, in which case a `DoogieError::InvalidChild` error is returned. Otherwise it is
+    /// unlinked from its current position first, as with `append_child`.
+    pub fn prepend_child(&mut self, child: &mut Node) -> DoogieResult<()> {
+        self.check_child(child)?;
+        child.unlink();
+        let result: i32;
+        unsafe {
+            result = cmark_node_prepend_child(self.pointer(), child.pointer());
+        }
 
-        Ok(result)
+        match result {
+            1 => {
+                child.manager().untrack_root(&child.pointer());
+                Ok(())
+            }
+            i => Err(DoogieError::ReturnCode(i as u32)),
+        }
     }
 
-    /// Renders the document AST rooted at the current `Node` into textual CommonMark form
-    pub fn render_commonmark(&self) -> String {
+    /// Insert the given `Node` as the previous sibling of the current `Node` if possible
+    ///
+    /// As with `append_child`, the given `Node` is unlinked from its current position first and
+    /// the rules of the CommonMark AST must be respected with regard to the parent of the current
+    /// `Node`, a `DoogieError::InvalidChild` error being returned otherwise. The current `Node`
+    /// must have a parent and cannot be inserted next to itself, as checked by `check_sibling`. An
+    /// error will be returned along with the libcmark error code if the operation fails.
+    pub fn insert_before(&mut self, sibling: &mut Node) -> DoogieResult<()> {
+        self.check_sibling(sibling)?;
+        sibling.unlink();
+        let result: i32;
         unsafe {
-            CStr::from_ptr(cmark_render_commonmark(self.pointer(), 0))
-                .to_string_lossy()
-                .into_owned()
+            result = cmark_node_insert_before(self.pointer(), sibling.pointer());
+        }
+
+        match result {
+            1 => {
+                sibling.manager().untrack_root(&sibling.pointer());
+                Ok(())
+            }
+            i => Err(DoogieError::ReturnCode(i as u32)),
         }
     }
 
-    /// Renders the document AST rooted at the current `Node` into textual xml form
-    pub fn render_xml(&self) -> String {
+    /// Insert the given `Node` as the next sibling of the current `Node` if possible
+    ///
+    /// As with `insert_before`, the given `Node` is unlinked from its current position first and
+    /// the rules of the CommonMark AST must be respected with regard to the parent of the current
+    /// `Node`, a `DoogieError::InvalidChild` error being returned otherwise. The current `Node`
+    /// must have a parent and cannot be inserted next to itself, as checked by `check_sibling`. An
+    /// error will be returned along with the libcmark error code if the operation fails.
+    pub fn insert_after(&mut self, sibling: &mut Node) -> DoogieResult<()> {
+        self.check_sibling(sibling)?;
+        sibling.unlink();
+        let result: i32;
         unsafe {
-            CStr::from_ptr(cmark_render_xml(self.pointer(), 0))
-                .to_string_lossy()
-                .into_owned()
+            result = cmark_node_insert_after(self.pointer(), sibling.pointer());
         }
-    }
 
-    /// Returns an iterator over the `Node`s of the document subtree rooted at the current `Node`
-    pub fn iter(&self) -> NodeIterator {
-        NodeIterator::new(self.pointer())
+        match result {
+            1 => {
+                sibling.manager().untrack_root(&sibling.pointer());
+                Ok(())
+            }
+            i => Err(DoogieError::ReturnCode(i as u32)),
+        }
     }
 
-    /// Returns the start line from the original CMark document corresponding to the current `Node`
-    pub fn get_start_line(&self) -> u32 {
-        unsafe { cmark_node_get_start_line(self.pointer()) as u32 }
+    /// Replace the current `Node` in the document AST with the given `Node` if possible
+    ///
+    /// The replacement is unlinked from its current position first and keeps all of its children.
+    /// After a successful replacement the current `Node` has no parent or siblings and, as after
+    /// `unlink`, is the root of its own subtree.
+    ///
+    /// The replacement is checked with `check_sibling` before anything is unlinked, so a
+    /// `DoogieError::NoParent`, `DoogieError::InvalidChild` or `DoogieError::CyclicAppend` error
+    /// leaves both trees unchanged. An error will be returned along with the libcmark error code if
+    /// the operation fails.
+    pub fn replace_with(&mut self, replacement: &mut Node) -> DoogieResult<()> {
+        self.check_sibling(replacement)?;
+        replacement.unlink();
+        let result: i32;
+        unsafe {
+            result = cmark_node_replace(self.pointer(), replacement.pointer());
+        }
+
+        match result {
+            1 => {
+                replacement.manager().untrack_root(&replacement.pointer());
+                self.manager().track_root(&self.pointer());
+                Ok(())
+            }
+            i => Err(DoogieError::ReturnCode(i as u32)),
+        }
     }
 
-    /// Returns the start column from the original CMark document corresponding to this `Node
-    pub fn get_start_column(&self) -> u32 {
-        unsafe { cmark_node_get_start_column(self.pointer()) as u32 }
+    /// Determines if the given `Node` is a potentially valid child of the current `Node`
+    pub fn can_append_child(&self, child: &Node) -> DoogieResult<bool> {
+        let child_type = child.get_cmark_type()?;
+
+        Ok(self.valid_child_types()?.contains(&child_type))
     }
-}
 
-/// Represents the root `Node` of a document in the CommonMark AST
-pub struct Document {
-    resource: Resource,
-}
+    /// Returns the types of `Node` that are potentially valid children of the current `Node`
+    pub fn valid_child_types(&self) -> DoogieResult<&'static HashSet<NodeType>> {
+        let types: &'static HashSet<NodeType> = match *self {
+            Node::Document(_) => &DOCUMENT_CHILDREN,
+            Node::BlockQuote(_) => &BLOCK_QUOTE_CHILDREN,
+            Node::List(_) => &LIST_CHILDREN,
+            Node::Item(_) => &ITEM_CHILDREN,
+            Node::CodeBlock(_) => &CODE_BLOCK_CHILDREN,
+            Node::HtmlBlock(_) => &HTML_BLOCK_CHILDREN,
+            Node::CustomBlock(_) => &CUSTOM_BLOCK_CHILDREN,
+            Node::Paragraph(_) => &PARAGRAPH_CHILDREN,
+            Node::Heading(_) => &HEADING_CHILDREN,
+            Node::ThematicBreak(_) => &THEMATIC_BREAK_CHILDREN,
+            Node::Text(_) => &TEXT_CHILDREN,
+            Node::SoftBreak(_) => &SOFT_BREAK_CHILDREN,
+            Node::LineBreak(_) => &LINE_BREAK_CHILDREN,
+            Node::Code(_) => &CODE_CHILDREN,
+            Node::HtmlInline(_) => &INLINE_HTML_CHILDREN,
+            Node::CustomInline(_) => &CUSTOM_INLINE_CHILDREN,
+            Node::Emph(_) => &EMPH_CHILDREN,
+            Node::Strong(_) => &STRONG_CHILDREN,
+            Node::Link(_) => &LINK_CHILDREN,
+            Node::Image(_) => &IMAGE_CHILDREN,
+        };
 
-impl Document {
-    /// Constructs a new `Document`
-    pub fn new() -> Self {
-        Self {
-            resource: Resource::from_node_type(
-                NodeType::CMarkNodeDocument,
-                Rc::new(ResourceManager::new()),
-            ),
-        }
+        Ok(types)
     }
 
-    /// Consolidates all adjacent `Text` `Node`s in the document into single `Text` `Node`s.
-    pub fn consolidate_text_nodes(&mut self) {
-        unsafe {
-            cmark_consolidate_text_nodes(self.resource.pointer);
+    /// Determines if the subtree rooted at the given `Node` has the same structure as the subtree
+    /// rooted at the current `Node`
+    ///
+    /// Unlike `==`, which compares node identity, this walks both subtrees in lockstep comparing
+    /// node types and attributes: literal content, fence info, link and image URLs and titles,
+    /// heading levels and list type, delimiter, start and tightness. Source positions are ignored.
+    pub fn tree_eq(&self, other: &Node) -> DoogieResult<bool> {
+        let mut left = self.iter();
+        let mut right = other.iter();
+
+        loop {
+            match (left.next(), right.next()) {
+                (None, None) => return Ok(true),
+                (Some((a, a_event)), Some((b, b_event))) => {
+                    if a_event != b_event || !a.attributes_eq(&b)? {
+                        return Ok(false);
+                    }
+                }
+                _ => return Ok(false),
+            }
         }
     }
-}
 
-/// Represents a Block Quote element in CommonMark
-pub struct BlockQuote {
-    resource: Resource,
-}
+    /// Determines if the given `Node` has the same type and attributes as the current `Node`,
+    /// regardless of their children
+    fn attributes_eq(&self, other: &Node) -> DoogieResult<bool> {
+        let equal = match (self, other) {
+            (Node::Text(ref a), Node::Text(ref b)) => a.get_content()? == b.get_content()?,
+            (Node::Code(ref a), Node::Code(ref b)) => a.get_content()? == b.get_content()?,
+            (Node::HtmlBlock(ref a), Node::HtmlBlock(ref b)) => {
+                a.get_content()? == b.get_content()?
+            }
+            (Node::HtmlInline(ref a), Node::HtmlInline(ref b)) => {
+                a.get_content()? == b.get_content()?
+            }
+            (Node::CodeBlock(ref a), Node::CodeBlock(ref b)) => {
+                a.get_content()? == b.get_content()? && a.get_fence_info()? == b.get_fence_info()?
+            }
+            (Node::Heading(ref a), Node::Heading(ref b)) => a.get_level() == b.get_level(),
+            (Node::List(ref a), Node::List(ref b)) => {
+                a.get_list_type()? == b.get_list_type()?
+                    && a.get_delim_type()? == b.get_delim_type()?
+                    && a.get_start() == b.get_start()
+                    && a.is_tight() == b.is_tight()
+            }
+            (Node::Link(ref a), Node::Link(ref b)) => {
+                a.get_url()? == b.get_url()? && a.get_title()? == b.get_title()?
+            }
+            (Node::Image(ref a), Node::Image(ref b)) => {
+                a.get_url()? == b.get_url()? && a.get_title()? == b.get_title()?
+            }
+            _ => self.get_cmark_type()? == other.get_cmark_type()?,
+        };
 
-impl BlockQuote {
-    /// Constructs a new `BlockQuote`
-    pub fn new() -> Self {
-        Self {
-            resource: Resource::from_node_type(
-                NodeType::CMarkNodeBlockQuote,
-                Rc::new(ResourceManager::new()),
-            ),
+        Ok(equal)
+    }
+
+    /// Returns a `DoogieError::InvalidChild` error if the given `Node` cannot be a child of the
+    /// current `Node`
+    ///
+    /// libcmark also refuses to make a `Node` a child of itself or of one of its descendants, for
+    /// which a `DoogieError::CyclicAppend` error is returned. Checking this up front keeps a
+    /// rejected `Node` from being unlinked from its tree.
+    fn check_child(&self, child: &Node) -> DoogieResult<()> {
+        if !self.can_append_child(child)? {
+            return Err(DoogieError::InvalidChild {
+                parent: self.get_cmark_type()?,
+                child: child.get_cmark_type()?,
+            });
         }
+        if child == self || child.is_ancestor_of(self)? {
+            return Err(DoogieError::CyclicAppend);
+        }
+
+        Ok(())
     }
-}
 
-/// Represents a List element in CommonMark
-///
-/// Lists are meta-containers in that they are classified as container blocks in CommonMark, but can
-/// only contain `Item` elements as children.
-pub struct List {
-    resource: Resource,
-}
+    /// Returns an error if the given `Node` cannot be placed next to the current `Node`
+    ///
+    /// The current `Node` must have a parent, a `DoogieError::NoParent` error being returned
+    /// otherwise, and the given `Node` must pass `check_child` for that parent. Placing the current
+    /// `Node` next to itself returns a `DoogieError::CyclicAppend` error.
+    fn check_sibling(&self, sibling: &Node) -> DoogieResult<()> {
+        if sibling == self {
+            return Err(DoogieError::CyclicAppend);
+        }
 
-impl List {
-    /// Constructs a new `List`
-    pub fn new() -> Self {
-        Self {
-            resource: Resource::from_node_type(
-                NodeType::CMarkNodeList,
-                Rc::new(ResourceManager::new()),
-            ),
+        match self.parent()? {
+            Some(parent) => parent.check_child(sibling),
+            None => Err(DoogieError::NoParent),
         }
     }
 
-    /// Returns an enum representing the type of list i.e. Bullet or Ordered
-    pub fn get_list_type(&self) -> DoogieResult<ListType> {
-        unsafe { ListType::try_from(cmark_node_get_list_type(self.resource.pointer) as u32) }
+    /// Renders the document AST rooted at the current `Node` into textual CommonMark form
+    pub fn render_commonmark(&self) -> String {
+        self.render_commonmark_with_options(&RenderOptions::default())
     }
 
-    /// Returns the delimiter type used in the case of ordered lists.
-    pub fn get_delim_type(&self) -> DoogieResult<DelimType> {
-        unsafe { DelimType::try_from(cmark_node_get_list_delim(self.resource.pointer) as u32) }
+    /// Renders the document AST rooted at the current `Node` into textual CommonMark form using the
+    /// given options
+    pub fn render_commonmark_with_options(&self, options: &RenderOptions) -> String {
+        unsafe {
+            CStr::from_ptr(cmark_render_commonmark(self.pointer(), u32::from(options) as c_int))
+                .to_string_lossy()
+                .into_owned()
+        }
     }
-}
 
-/// Represents a List Item in CommonMark
-pub struct Item {
-    resource: Resource,
-}
+    /// Renders the document AST rooted at the current `Node` into textual CommonMark form, with
+    /// hard line breaks rendered as a trailing backslash instead of trailing spaces
+    ///
+    /// The rendered text is parsed again so that the lines of code and HTML blocks are left as they
+    /// are, even when they end in two spaces.
+    pub fn render_commonmark_backslash_hardbreaks(&self) -> DoogieResult<String> {
+        backslash_hardbreaks(&self.render_commonmark())
+    }
 
-impl Item {
-    /// Constructs a new `Item`
-    pub fn new() -> Self {
-        Self {
-            resource: Resource::from_node_type(
-                NodeType::CMarkNodeItem,
-                Rc::new(ResourceManager::new()),
-            ),
+    /// Renders the document AST rooted at the current `Node` into textual CommonMark form, using
+    /// the bullet list markers of the original source
+    ///
+    /// libcmark renders every bullet list with `-`. The markers are only recorded by
+    /// `parse_document_preserving_markers`, and are then available from any `Node` of the tree. The
+    /// rendered text is parsed again to find where each item starts and only the markers found
+    /// there are replaced. Lists from the other parse functions, including those of a
+    /// `StreamingParser`, and lists created with `Node::from_type` or copied with
+    /// `Node::deep_copy` have no recorded marker and keep `-`.
+    pub fn render_commonmark_preserving_markers(&self) -> DoogieResult<String> {
+        let mut markers = Vec::new();
+
+        for (node, event) in self.iter() {
+            if event != IterEventType::Enter {
+                continue;
+            }
+
+            if let Node::Item(_) = node {
+                if let Some(Node::List(ref list)) = node.parent()? {
+                    if list.get_list_type()? == ListType::CMarkBulletList {
+                        markers.push(recorded_list_marker(list.resource.pointer).unwrap_or('-'));
+                    }
+                }
+            }
         }
+
+        restore_bullet_markers(&self.render_commonmark(), &markers)
     }
-}
 
-/// Represents a Code Block in CommonMark
-pub struct CodeBlock {
-    resource: Resource,
-}
+    /// Renders the document AST rooted at the current `Node` into textual xml form
+    pub fn render_xml(&self) -> String {
+        self.render_xml_with_options(&RenderOptions::default())
+    }
 
-impl CodeBlock {
-    /// Constructs a new `CodeBlock`
-    pub fn new() -> Self {
-        Self {
-            resource: Resource::from_node_type(
-                NodeType::CMarkNodeCodeBlock,
-                Rc::new(ResourceManager::new()),
-            ),
+    /// Renders the document AST rooted at the current `Node` into textual xml form using the
+    /// given options
+    pub fn render_xml_with_options(&self, options: &RenderOptions) -> String {
+        unsafe {
+            CStr::from_ptr(cmark_render_xml(self.pointer(), u32::from(options) as c_int))
+                .to_string_lossy()
+                .into_owned()
         }
     }
 
-    /// Returns the info text in the case of a Fenced Code Block
-    pub fn get_fence_info(&self) -> DoogieResult<String> {
+    /// Renders the document AST rooted at the current `Node` into textual html form
+    pub fn render_html(&self) -> String {
+        self.render_html_with_options(&RenderOptions::default())
+    }
+
+    /// Renders the document AST rooted at the current `Node` into textual html form using the
+    /// given options
+    pub fn render_html_with_options(&self, options: &RenderOptions) -> String {
         unsafe {
-            Ok(
-                CStr::from_ptr(cmark_node_get_fence_info(self.resource.pointer))
-                    .to_str()?
-                    .to_string(),
-            )
+            CStr::from_ptr(cmark_render_html(self.pointer(), u32::from(options) as c_int))
+                .to_string_lossy()
+                .into_owned()
         }
     }
 
-    /// Sets the info text for the code block
-    pub fn set_fence_info(&mut self, info: &String) -> DoogieResult<u32> {
-        let info = CString::new(info.as_bytes())?;
-        let result: i32;
+    /// Renders the document AST rooted at the current `Node` into a canonical CommonMark form
+    ///
+    /// The rendered CommonMark is parsed and rendered again, so that documents differing only in
+    /// their markup, e.g. in emphasis delimiters, heading style, bullet markers, escaping or
+    /// surrounding whitespace, produce identical output. Normalizing the output again leaves it
+    /// unchanged.
+    pub fn normalize(&self) -> DoogieResult<String> {
+        let rendered = self.render_commonmark();
+        Ok(parse_document_checked(&rendered)?.render_commonmark())
+    }
+
+    /// Renders the document AST rooted at the current `Node` into textual CommonMark form, writing
+    /// it to the given writer without copying it into a `String`
+    pub fn write_commonmark<W: Write>(&self, writer: &mut W) -> DoogieResult<()> {
+        self.write_commonmark_with_options(writer, &RenderOptions::default())
+    }
+
+    /// Renders the document AST rooted at the current `Node` into textual CommonMark form using the
+    /// given options, writing it to the given writer without copying it into a `String`
+    pub fn write_commonmark_with_options<W: Write>(
+        &self,
+        writer: &mut W,
+        options: &RenderOptions,
+    ) -> DoogieResult<()> {
+        let rendered;
         unsafe {
-            result = cmark_node_set_fence_info(self.resource.pointer, info.as_ptr());
+            rendered = cmark_render_commonmark(self.pointer(), u32::from(options) as c_int);
         }
 
-        match result {
-            1 => Ok(1),
-            err => Err(DoogieError::ReturnCode(err as u32)),
-        }
+        write_rendered(rendered, writer)
     }
 
-    /// Returns the textual content of the current Code Block element
-    pub fn get_content(&self) -> DoogieResult<String> {
-        let result;
+    /// Renders the document AST rooted at the current `Node` into textual html form, writing it to
+    /// the given writer without copying it into a `String`
+    pub fn write_html<W: Write>(&self, writer: &mut W) -> DoogieResult<()> {
+        self.write_html_with_options(writer, &RenderOptions::default())
+    }
+
+    /// Renders the document AST rooted at the current `Node` into textual html form using the given
+    /// options, writing it to the given writer without copying it into a `String`
+    pub fn write_html_with_options<W: Write>(
+        &self,
+        writer: &mut W,
+        options: &RenderOptions,
+    ) -> DoogieResult<()> {
+        let rendered;
         unsafe {
-            result = cmark_node_get_literal(self.resource.pointer);
+            rendered = cmark_render_html(self.pointer(), u32::from(options) as c_int);
         }
 
-        if result.is_null() {
-            return Ok(String::new());
-        } else {
-            unsafe {
-                return Ok(CStr::from_ptr(result).to_str()?.to_string());
-            }
+        write_rendered(rendered, writer)
+    }
+
+    /// Renders the document AST rooted at the current `Node` into LaTeX, wrapping lines at the
+    /// given width or not at all if it is 0
+    pub fn render_latex(&self, width: i32) -> String {
+        unsafe {
+            CStr::from_ptr(cmark_render_latex(self.pointer(), 0, width))
+                .to_string_lossy()
+                .into_owned()
         }
     }
 
-    /// Sets the textual content of the current Code Block element
-    pub fn set_content(&mut self, content: &String) -> DoogieResult<u32> {
-        let content = CString::new(content.as_bytes())?;
-        let result: i32;
+    /// Renders the document AST rooted at the current `Node` into a groff man page, wrapping lines
+    /// at the given width or not at all if it is 0
+    pub fn render_man(&self, width: i32) -> String {
         unsafe {
-            result = cmark_node_set_literal(self.resource.pointer, content.as_ptr());
+            CStr::from_ptr(cmark_render_man(self.pointer(), 0, width))
+                .to_string_lossy()
+                .into_owned()
         }
+    }
 
-        match result {
-            1 => Ok(1 as u32),
-            i => Err(DoogieError::ReturnCode(i as u32)),
+    /// Renders the document AST rooted at the current `Node` into the given textual format
+    pub fn render(&self, format: RenderFormat) -> String {
+        match format {
+            RenderFormat::CommonMark => self.render_commonmark(),
+            RenderFormat::Xml => self.render_xml(),
+            RenderFormat::Html => self.render_html(),
         }
     }
-}
 
-/// Represents a block of HTML in CommonMark
-pub struct HtmlBlock {
-    resource: Resource,
-}
+    /// Returns an iterator over the `Node`s of the document subtree rooted at the current `Node`
+    pub fn iter(&self) -> NodeIterator {
+        NodeIterator::new(self.pointer())
+    }
 
-impl HtmlBlock {
-    /// Constructs a new `HtmlBlock`
-    pub fn new() -> Self {
-        Self {
-            resource: Resource::from_node_type(
-                NodeType::CMarkNodeHtmlBlock,
-                Rc::new(ResourceManager::new()),
-            ),
-        }
+    /// Returns an iterator over the `NodeType`s of the document subtree rooted at the current
+    /// `Node`
+    pub fn iter_types(&self) -> NodeTypeIterator {
+        NodeTypeIterator::new(self.pointer())
     }
-}
 
-/// Represents an ambiguous Block Element
-pub struct CustomBlock {
-    resource: Resource,
-}
+    /// Returns an iterator over the direct children of the current `Node`
+    pub fn children(&self) -> ChildIterator {
+        let first_child;
+        unsafe {
+            first_child = cmark_node_first_child(self.pointer());
+        }
 
-impl CustomBlock {
-    /// Constructs a new `CustomBlock`
-    pub fn new() -> Self {
-        Self {
-            resource: Resource::from_node_type(
-                NodeType::CMarkNodeCustomBlock,
-                Rc::new(ResourceManager::new()),
-            ),
+        ChildIterator { next: first_child }
+    }
+
+    /// Returns an iterator over the siblings following the current `Node`, nearest first
+    pub fn following_siblings(&self) -> SiblingIterator {
+        SiblingIterator {
+            current: self.pointer(),
+            forward: true,
         }
     }
-}
 
-/// Represents a Paragraph element in CommonMark
-pub struct Paragraph {
-    resource: Resource,
-}
+    /// Returns an iterator over the siblings preceding the current `Node`, nearest first
+    pub fn preceding_siblings(&self) -> SiblingIterator {
+        SiblingIterator {
+            current: self.pointer(),
+            forward: false,
+        }
+    }
 
-impl Paragraph {
-    /// Constructs a new `Paragraph`
-    pub fn new() -> Self {
-        Self {
-            resource: Resource::from_node_type(
-                NodeType::CMarkNodeParagraph,
-                Rc::new(ResourceManager::new()),
-            ),
+    /// Returns an iterator over the ancestors of the current `Node`, from its parent up to the
+    /// root of the tree
+    pub fn ancestors(&self) -> AncestorIterator {
+        AncestorIterator {
+            current: self.pointer(),
         }
     }
-}
 
-/// Represents a Heading element in CommonMark
-pub struct Heading {
-    resource: Resource,
-}
+    /// Returns the start line from the original CMark document corresponding to the current `Node`
+    pub fn get_start_line(&self) -> u32 {
+        unsafe { cmark_node_get_start_line(self.pointer()) as u32 }
+    }
 
-impl Heading {
-    /// Constructs a new `Heading`
-    pub fn new() -> Self {
-        Self {
-            resource: Resource::from_node_type(
-                NodeType::CMarkNodeHeading,
-                Rc::new(ResourceManager::new()),
-            ),
-        }
+    /// Returns the start column from the original CMark document corresponding to this `Node
+    pub fn get_start_column(&self) -> u32 {
+        unsafe { cmark_node_get_start_column(self.pointer()) as u32 }
     }
 
-    /// Returns the heading level of the current Heading
-    pub fn get_level(&self) -> usize {
-        unsafe { cmark_node_get_heading_level(self.resource.pointer) as usize }
+    /// Returns the end line from the original CMark document corresponding to the current `Node`
+    pub fn get_end_line(&self) -> u32 {
+        unsafe { cmark_node_get_end_line(self.pointer()) as u32 }
     }
-}
 
-/// Represents a Thematic Break element in CommonMark
-pub struct ThematicBreak {
-    resource: Resource,
-}
+    /// Returns the end column from the original CMark document corresponding to the current `Node`
+    pub fn get_end_column(&self) -> u32 {
+        unsafe { cmark_node_get_end_column(self.pointer()) as u32 }
+    }
 
-impl ThematicBreak {
-    /// Constructs a new `ThematicBreak`
-    pub fn new() -> Self {
-        Self {
-            resource: Resource::from_node_type(
-                NodeType::CMarkNodeThematicBreak,
-                Rc::new(ResourceManager::new()),
-            ),
+    /// Returns the range of the current `Node` in the given source as an LSP range
+    ///
+    /// libcmark positions are 1-based, with columns counted in bytes and an inclusive end, while
+    /// LSP positions are 0-based with characters counted in UTF-16 code units and an exclusive
+    /// end. `source` must be the text the document was parsed from. Returns `None` when libcmark
+    /// has not recorded a position for the current `Node`.
+    pub fn lsp_range(&self, source: &str) -> DoogieResult<Option<LspRange>> {
+        let start_line = self.get_start_line();
+        if start_line == 0 {
+            return Ok(None);
         }
-    }
-}
 
-/// Represents a Text element in CommonMark
-pub struct Text {
-    resource: Resource,
-}
+        let lines: Vec<&str> = source.split('\n').collect();
+        let position = |line: u32, byte_offset: u32| {
+            let text = lines.get(line as usize - 1).cloned().unwrap_or("");
+            LspPosition {
+                line: line - 1,
+                character: utf16_column(text, byte_offset as usize),
+            }
+        };
+        let end_line = self.get_end_line().max(start_line);
 
-impl Text {
-    /// Constructs a new `Text`
-    pub fn new() -> Self {
-        Text {
-            resource: Resource::from_node_type(
-                NodeType::CMarkNodeText,
-                Rc::new(ResourceManager::new()),
-            ),
+        Ok(Some(LspRange {
+            start: position(start_line, self.get_start_column().saturating_sub(1)),
+            end: position(end_line, self.get_end_column()),
+        }))
+    }
+
+    /// Returns the distinct destination URLs of every `Link` and `Image` in the subtree rooted at
+    /// the current `Node`, in the order they are first encountered
+    pub fn all_urls(&self) -> DoogieResult<Vec<String>> {
+        let mut urls: Vec<String> = Vec::new();
+
+        for (node, event) in self.iter() {
+            if event != IterEventType::Enter {
+                continue;
+            }
+
+            let url = match node {
+                Node::Link(ref link) => link.get_url()?,
+                Node::Image(ref image) => image.get_url()?,
+                _ => continue,
+            };
+
+            if !urls.contains(&url) {
+                urls.push(url);
+            }
         }
+
+        Ok(urls)
     }
 
-    /// Returns the textual content of the current Text element
-    pub fn get_content(&self) -> DoogieResult<String> {
-        let result;
-        unsafe {
-            result = cmark_node_get_literal(self.resource.pointer);
+    /// Returns the number of nodes below the current `Node`, inline nodes included
+    pub fn count_descendants(&self) -> usize {
+        self.iter()
+            .filter(|&(_, ref event)| *event == IterEventType::Enter)
+            .count()
+            .saturating_sub(1)
+    }
+
+    /// Replaces every occurrence of `from` in the content of the `Text`, `Code` and `CodeBlock`
+    /// nodes of the subtree rooted at the current `Node` with `to`
+    ///
+    /// Occurrences spanning several nodes, e.g. across emphasis, are not replaced. Returns the
+    /// number of nodes changed.
+    pub fn replace_text(&self, from: &str, to: &str) -> DoogieResult<usize> {
+        let mut changed = 0;
+        if from.is_empty() {
+            return Ok(changed);
         }
 
-        if result.is_null() {
-            return Ok(String::new());
-        } else {
-            unsafe {
-                return Ok(CStr::from_ptr(result).to_str()?.to_string());
+        for (mut node, event) in self.iter() {
+            if event != IterEventType::Enter {
+                continue;
+            }
+
+            let content = match node {
+                Node::Text(ref text) => text.get_content()?,
+                Node::Code(ref code) => code.get_content()?,
+                Node::CodeBlock(ref block) => block.get_content()?,
+                _ => continue,
+            };
+            if !content.contains(from) {
+                continue;
             }
+
+            let replaced = content.replace(from, to);
+            match node {
+                Node::Text(ref mut text) => text.set_content(&replaced)?,
+                Node::Code(ref mut code) => code.set_content(&replaced)?,
+                Node::CodeBlock(ref mut block) => block.set_content(&replaced)?,
+                _ => continue,
+            };
+            changed += 1;
         }
+
+        Ok(changed)
     }
 
-    /// Sets the textual content of the current Text element
-    pub fn set_content(&mut self, content: &String) -> DoogieResult<u32> {
-        let content = CString::new(content.as_bytes())?;
-        let result: i32;
-        unsafe {
-            result = cmark_node_set_literal(self.resource.pointer, content.as_ptr());
+    /// Sets the textual content of the current `Node`, rejecting newlines in the content of
+    /// inline nodes
+    ///
+    /// Returns `DoogieError::InvalidContent` if the current `Node` is a `Text`, `Code` or
+    /// `HtmlInline` and the content contains a line ending, either `'\n'` or `'\r'`, which would
+    /// corrupt the rendered document.
+    /// `CodeBlock` and `HtmlBlock` content may span several lines. Returns
+    /// `DoogieError::UnsupportedNodeType` for nodes without content.
+    pub fn set_content_validated(&mut self, content: &str) -> DoogieResult<u32> {
+        let node_type = self.get_cmark_type()?;
+        let content = content.to_string();
+        let inline = match *self {
+            Node::Text(_) | Node::Code(_) | Node::HtmlInline(_) => true,
+            Node::CodeBlock(_) | Node::HtmlBlock(_) => false,
+            _ => return Err(DoogieError::UnsupportedNodeType(node_type)),
+        };
+        if inline && content.contains(|c| c == '\n' || c == '\r') {
+            return Err(DoogieError::InvalidContent(node_type));
         }
 
-        match result {
-            1 => Ok(1 as u32),
-            i => Err(DoogieError::ReturnCode(i as u32)),
+        match *self {
+            Node::Text(ref mut text) => text.set_content(&content),
+            Node::Code(ref mut code) => code.set_content(&content),
+            Node::HtmlInline(ref mut html) => html.set_content(&content),
+            Node::CodeBlock(ref mut block) => block.set_content(&content),
+            Node::HtmlBlock(ref mut html) => html.set_content(&content),
+            _ => Err(DoogieError::UnsupportedNodeType(node_type)),
         }
     }
-}
 
-/// Represents a Soft Break element in CommonMark
-pub struct SoftBreak {
-    resource: Resource,
-}
+    /// Returns every `Node` of the given type in the subtree rooted at the current `Node`, in
+    /// document order
+    ///
+    /// The current `Node` is included when it is itself of the given type.
+    pub fn find_all(&self, node_type: NodeType) -> DoogieResult<Vec<Node>> {
+        let mut found = Vec::new();
 
-impl SoftBreak {
-    /// Constructs a new `SoftBreak`
-    pub fn new() -> Self {
-        Self {
-            resource: Resource::from_node_type(
-                NodeType::CMarkNodeSoftbreak,
-                Rc::new(ResourceManager::new()),
-            ),
+        for (node, event) in self.iter() {
+            if event == IterEventType::Enter && node.get_cmark_type()? == node_type {
+                found.push(node);
+            }
         }
+
+        Ok(found)
     }
-}
 
-/// Represents a Line Break element in CommonMark
-pub struct LineBreak {
-    resource: Resource,
-}
+    /// Returns every run of `Text` and inline `Code` in the subtree rooted at the current `Node`
+    /// tagged with the formatting of its enclosing inline elements
+    pub fn formatted_runs(&self) -> DoogieResult<Vec<FormattedRun>> {
+        let mut runs = Vec::new();
 
-impl LineBreak {
-    /// Constructs a new `LineBreak`
-    pub fn new() -> Self {
-        Self {
-            resource: Resource::from_node_type(
-                NodeType::CMarkNodeLinebreak,
-                Rc::new(ResourceManager::new()),
-            ),
+        for (node, event) in self.iter() {
+            if event != IterEventType::Enter {
+                continue;
+            }
+
+            let (text, code) = match node {
+                Node::Text(ref text) => (text.get_content()?, false),
+                Node::Code(ref code) => (code.get_content()?, true),
+                _ => continue,
+            };
+            let mut run = FormattedRun {
+                text,
+                emphasis: false,
+                strong: false,
+                code,
+                link_url: None,
+            };
+
+            let mut ancestor = node.parent()?;
+            while let Some(parent) = ancestor {
+                match parent {
+                    Node::Emph(_) => run.emphasis = true,
+                    Node::Strong(_) => run.strong = true,
+                    Node::Link(ref link) => {
+                        if run.link_url.is_none() {
+                            run.link_url = Some(link.get_url()?);
+                        }
+                    }
+                    Node::Image(_) | Node::CustomInline(_) => (),
+                    _ => break,
+                }
+                ancestor = parent.parent()?;
+            }
+
+            runs.push(run);
         }
+
+        Ok(runs)
     }
-}
 
-/// Represents an inline Code element in CommonMark
-pub struct Code {
-    resource: Resource,
-}
+    /// Replaces every descendant of the given type in the subtree rooted at the current `Node` with
+    /// the `Node` that `f` produces for it
+    ///
+    /// Matches nested within another match are not visited separately since they are replaced
+    /// along with their ancestor. Returns the number of nodes replaced.
+    pub fn transform_type<F>(&self, from: NodeType, f: F) -> DoogieResult<usize>
+    where
+        F: Fn(&Node) -> DoogieResult<Node>,
+    {
+        let mut targets = Vec::new();
+
+        for (node, event) in self.iter() {
+            if event != IterEventType::Enter || node == *self || node.get_cmark_type()? != from {
+                continue;
+            }
 
-impl Code {
-    /// Constructs a new `Code`
-    pub fn new() -> Self {
-        Self {
-            resource: Resource::from_node_type(
-                NodeType::CMarkNodeCode,
-                Rc::new(ResourceManager::new()),
-            ),
+            let mut nested = false;
+            let mut ancestor = node.parent()?;
+            while let Some(parent) = ancestor {
+                if parent == *self {
+                    break;
+                }
+                if parent.get_cmark_type()? == from {
+                    nested = true;
+                    break;
+                }
+                ancestor = parent.parent()?;
+            }
+
+            if !nested {
+                targets.push(node);
+            }
         }
-    }
 
-    /// Returns the textual content of the current Text element
-    pub fn get_content(&self) -> DoogieResult<String> {
-        let result;
-        unsafe {
-            result = cmark_node_get_literal(self.resource.pointer);
+        let mut count = 0;
+        for mut node in targets {
+            let mut replacement = f(&node)?;
+            node.replace_with(&mut replacement)?;
+            count += 1;
         }
 
-        if result.is_null() {
-            return Ok(String::new());
-        } else {
-            unsafe {
-                return Ok(CStr::from_ptr(result).to_str()?.to_string());
+        Ok(count)
+    }
+
+    /// Returns the URL of every `Image` in the subtree rooted at the current `Node` in document
+    /// order, including repeated URLs
+    pub fn image_urls(&self) -> DoogieResult<Vec<String>> {
+        let mut urls = Vec::new();
+
+        for (node, event) in self.iter() {
+            if event != IterEventType::Enter {
+                continue;
+            }
+
+            if let Node::Image(ref image) = node {
+                urls.push(image.get_url()?);
             }
         }
+
+        Ok(urls)
     }
 
-    /// Sets the textual content of the current Text element
-    pub fn set_content(&mut self, content: &String) -> DoogieResult<u32> {
-        let content = CString::new(content.as_bytes())?;
-        let result: i32;
-        unsafe {
-            result = cmark_node_set_literal(self.resource.pointer, content.as_ptr());
-        }
+    /// Inserts a `ThematicBreak` before every level 1 `Heading` among the children of the current
+    /// `Node` except the first one, returning the number of breaks inserted
+    pub fn insert_section_separators(&self) -> DoogieResult<usize> {
+        let mut count = 0;
+        let mut seen_first = false;
 
-        match result {
-            1 => Ok(1 as u32),
-            i => Err(DoogieError::ReturnCode(i as u32)),
+        for mut child in self.child_nodes()? {
+            let is_top_level = match child {
+                Node::Heading(ref heading) => heading.get_level() == 1,
+                _ => false,
+            };
+
+            if !is_top_level {
+                continue;
+            }
+
+            if !seen_first {
+                seen_first = true;
+                continue;
+            }
+
+            let mut separator = Node::from_type(NodeType::CMarkNodeThematicBreak)?;
+            child.insert_before(&mut separator)?;
+            count += 1;
         }
+
+        Ok(count)
     }
-}
 
-/// Represents an inline HTML element in CommonMark
-pub struct HtmlInline {
-    resource: Resource,
-}
+    /// Merges `Text` nodes that are separated only by a `SoftBreak` within the `Emph` and `Strong`
+    /// elements of the subtree rooted at the current `Node`
+    ///
+    /// Each collapsed soft break becomes a single space in the merged text. Returns the number of
+    /// merges performed.
+    pub fn merge_split_inline(&self) -> DoogieResult<usize> {
+        let containers: Vec<Node> = self.iter()
+            .filter(|&(ref node, ref event)| {
+                *event == IterEventType::Enter && match *node {
+                    Node::Emph(_) | Node::Strong(_) => true,
+                    _ => false,
+                }
+            })
+            .map(|(node, _)| node)
+            .collect();
+        let mut count = 0;
+
+        for container in containers {
+            let mut current = container.first_child()?;
+
+            while let Some(mut node) = current {
+                let is_text = match node {
+                    Node::Text(_) => true,
+                    _ => false,
+                };
+
+                if is_text {
+                    while node.merge_following_text()? {
+                        count += 1;
+                    }
+                }
 
-impl HtmlInline {
-    /// Constructs a new `HtmlInline`
-    pub fn new() -> Self {
-        Self {
-            resource: Resource::from_node_type(
-                NodeType::CMarkNodeHtmlInline,
-                Rc::new(ResourceManager::new()),
-            ),
+                current = node.next_sibling()?;
+            }
         }
+
+        Ok(count)
     }
-}
 
-/// Represents an ambiguous inline element
-pub struct CustomInline {
-    resource: Resource,
-}
+    /// Merges the `Text` node following a `SoftBreak` sibling into the current `Text` node
+    ///
+    /// Returns whether a merge took place.
+    fn merge_following_text(&mut self) -> DoogieResult<bool> {
+        let mut soft_break = match self.next_sibling()? {
+            Some(node @ Node::SoftBreak(_)) => node,
+            _ => return Ok(false),
+        };
+        let mut following = match soft_break.next_sibling()? {
+            Some(node @ Node::Text(_)) => node,
+            _ => return Ok(false),
+        };
+        let merged = match (&*self, &following) {
+            (&Node::Text(ref first), &Node::Text(ref second)) => {
+                format!("{} {}", first.get_content()?, second.get_content()?)
+            }
+            _ => return Ok(false),
+        };
 
-impl CustomInline {
-    /// Constructs a new `CustomInline`
-    pub fn new() -> Self {
-        Self {
-            resource: Resource::from_node_type(
-                NodeType::CMarkNodeCustomInline,
-                Rc::new(ResourceManager::new()),
-            ),
+        if let Node::Text(ref mut text) = *self {
+            text.set_content(&merged)?;
         }
+        soft_break.unlink();
+        following.unlink();
+
+        Ok(true)
     }
-}
 
-/// Represenets an Emph element in CommonMark
-pub struct Emph {
-    resource: Resource,
-}
+    /// Returns a GraphViz DOT digraph describing the subtree rooted at the current `Node`
+    ///
+    /// Each node is labeled with its type and a short preview of its literal content, if it has
+    /// any, and an edge is declared for every parent to child relationship.
+    pub fn to_dot(&self) -> DoogieResult<String> {
+        let mut dot = String::from("digraph {\n");
+
+        for (node, event) in self.iter() {
+            if event != IterEventType::Enter {
+                continue;
+            }
 
-impl Emph {
-    /// Constructs a new `Emph`
-    pub fn new() -> Self {
-        Self {
-            resource: Resource::from_node_type(
-                NodeType::CMarkNodeEmph,
-                Rc::new(ResourceManager::new()),
-            ),
+            let mut label = node.get_cmark_type_string()?;
+            let literal = node.get_literal()?;
+            if !literal.is_empty() {
+                let preview: String = literal.chars().take(20).collect();
+                label.push_str(": ");
+                label.push_str(&preview);
+            }
+            dot.push_str(&format!(
+                "    n{} [label=\"{}\"];\n",
+                node.get_id(),
+                escape_dot_label(&label)
+            ));
+
+            if node != *self {
+                if let Some(parent) = node.parent()? {
+                    dot.push_str(&format!("    n{} -> n{};\n", parent.get_id(), node.get_id()));
+                }
+            }
         }
+        dot.push_str("}\n");
+
+        Ok(dot)
     }
-}
 
-/// Represents a Strong element in CommonMark
-pub struct Strong {
-    resource: Resource,
-}
+    /// Renders the subtree rooted at the current `Node` into textual html form after resolving
+    /// every relative `Link` and `Image` URL against the given base URL
+    ///
+    /// Root-relative URLs such as `/img.png` resolve against the scheme and host of the base URL.
+    /// Other relative URLs are joined onto the base URL, which is treated as a directory.
+    ///
+    /// The URLs are rewritten on a copy of the subtree so the current `Node` is left unmodified.
+    pub fn render_html_with_base(&self, base_url: &str) -> DoogieResult<String> {
+        let copy = self.deep_copy()?;
 
-impl Strong {
-    /// Constructs a new `Strong`
-    pub fn new() -> Self {
-        Self {
-            resource: Resource::from_node_type(
-                NodeType::CMarkNodeStrong,
-                Rc::new(ResourceManager::new()),
-            ),
+        for (mut node, event) in copy.iter() {
+            if event != IterEventType::Enter {
+                continue;
+            }
+
+            match node {
+                Node::Link(ref mut link) => {
+                    let url = resolve_url(base_url, &link.get_url()?);
+                    link.set_url(&url)?;
+                }
+                Node::Image(ref mut image) => {
+                    let url = resolve_url(base_url, &image.get_url()?);
+                    image.set_url(&url)?;
+                }
+                _ => (),
+            }
         }
+
+        Ok(copy.render_html())
     }
-}
 
-/// Represents a Link element in CommonMark
-pub struct Link {
-    resource: Resource,
-}
+    /// Returns an independent copy of the subtree rooted at the current `Node`
+    ///
+    /// Every node is recreated along with its attributes: literal content, link and image URLs and
+    /// titles, fence info, custom enter and exit text, heading levels and list type, delimiter,
+    /// start and tightness. The copy has no parent and is owned by its own `ResourceManager`, so it
+    /// can be modified without affecting the original.
+    pub fn deep_copy(&self) -> DoogieResult<Node> {
+        let copy = self.shallow_copy()?;
+        copy.manager().track_root(&copy.pointer());
+        self.copy_children_into(&copy)?;
+
+        Ok(copy)
+    }
 
-impl Link {
-    /// Constructs a new `Link`
-    pub fn new() -> Self {
-        Self {
-            resource: Resource::from_node_type(
-                NodeType::CMarkNodeLink,
-                Rc::new(ResourceManager::new()),
-            ),
+    /// Recursively appends copies of the children of the current `Node` to the given `Node`
+    fn copy_children_into(&self, target: &Node) -> DoogieResult<()> {
+        let mut target = target.itself()?;
+
+        for child in self.children() {
+            let mut copy = child.shallow_copy()?;
+            child.copy_children_into(&copy)?;
+            target.append_child(&mut copy)?;
         }
+
+        Ok(())
     }
 
-    /// Returns the URL portion of the Link
-    pub fn get_url(&self) -> DoogieResult<String> {
+    /// Returns a new `Node` of the same type and with the same attributes as the current `Node`,
+    /// but without children
+    fn shallow_copy(&self) -> DoogieResult<Node> {
+        let copy = Node::from_type(self.get_cmark_type()?)?;
+        let (source, target) = (self.pointer(), copy.pointer());
+        let mut results = Vec::new();
+
         unsafe {
-            Ok(CStr::from_ptr(cmark_node_get_url(self.resource.pointer))
-                .to_str()?
-                .to_string())
+            let literal = cmark_node_get_literal(source);
+            if !literal.is_null() {
+                results.push(cmark_node_set_literal(target, literal));
+            }
+            let url = cmark_node_get_url(source);
+            if !url.is_null() {
+                results.push(cmark_node_set_url(target, url));
+            }
+            let title = cmark_node_get_title(source);
+            if !title.is_null() {
+                results.push(cmark_node_set_title(target, title));
+            }
+            let info = cmark_node_get_fence_info(source);
+            if !info.is_null() {
+                results.push(cmark_node_set_fence_info(target, info));
+            }
+            let on_enter = cmark_node_get_on_enter(source);
+            if !on_enter.is_null() {
+                results.push(cmark_node_set_on_enter(target, on_enter));
+            }
+            let on_exit = cmark_node_get_on_exit(source);
+            if !on_exit.is_null() {
+                results.push(cmark_node_set_on_exit(target, on_exit));
+            }
+
+            match *self {
+                Node::Heading(_) => {
+                    let level = cmark_node_get_heading_level(source);
+                    results.push(cmark_node_set_heading_level(target, level));
+                }
+                Node::List(_) => {
+                    let list_type = cmark_node_get_list_type(source);
+                    results.push(cmark_node_set_list_type(target, list_type));
+                    let delim = cmark_node_get_list_delim(source);
+                    results.push(cmark_node_set_list_delim(target, delim));
+                    let start = cmark_node_get_list_start(source);
+                    results.push(cmark_node_set_list_start(target, start));
+                    let tight = cmark_node_get_list_tight(source);
+                    results.push(cmark_node_set_list_tight(target, tight));
+                }
+                _ => (),
+            }
+        }
+
+        match results.into_iter().find(|&result| result != 1) {
+            Some(result) => Err(DoogieError::ReturnCode(result as u32)),
+            None => Ok(copy),
         }
     }
 
-    /// Returns the title portion of the Link
-    pub fn get_title(&self) -> DoogieResult<String> {
-        unsafe {
-            Ok(CStr::from_ptr(cmark_node_get_title(self.resource.pointer))
-                .to_str()?
-                .to_string())
+    /// Counts the `Node`s of the subtree rooted at the current `Node` whose type satisfies the
+    /// given predicate
+    ///
+    /// The subtree is walked with `iter_types` so no `Node` wrappers are constructed.
+    pub fn count<F: Fn(NodeType) -> bool>(&self, pred: F) -> DoogieResult<usize> {
+        let mut count = 0;
+
+        for (node_type, event) in self.iter_types() {
+            if event == IterEventType::Enter && pred(node_type) {
+                count += 1;
+            }
         }
+
+        Ok(count)
     }
-}
 
-/// Represents an Image element in CommonMark
-pub struct Image {
-    resource: Resource,
-}
+    /// Returns the plain textual content of the subtree rooted at the current `Node`
+    ///
+    /// Inline formatting is dropped, soft and hard line breaks become newlines and every paragraph,
+    /// heading and code block is terminated by a newline. Raw html is omitted.
+    pub fn to_plain_text(&self) -> DoogieResult<String> {
+        self.collect_plain_text(true)
+    }
 
-impl Image {
-    /// Constructs a new `Image`
-    pub fn new() -> Self {
-        Self {
-            resource: Resource::from_node_type(
-                NodeType::CMarkNodeImage,
-                Rc::new(ResourceManager::new()),
-            ),
+    /// Collects the plain textual content of the subtree rooted at the current `Node`, optionally
+    /// leaving out inline code and code blocks
+    fn collect_plain_text(&self, include_code: bool) -> DoogieResult<String> {
+        let mut text = String::new();
+
+        for item in self.iter() {
+            match item {
+                (Node::Text(ref node), IterEventType::Enter) => {
+                    text.push_str(&node.get_content()?);
+                }
+                (Node::Code(ref node), IterEventType::Enter) => {
+                    if include_code {
+                        text.push_str(&node.get_content()?);
+                    }
+                }
+                (Node::CodeBlock(ref node), IterEventType::Enter) => {
+                    if include_code {
+                        let content = node.get_content()?;
+                        text.push_str(&content);
+                        if !content.ends_with('\n') {
+                            text.push('\n');
+                        }
+                    }
+                }
+                (Node::SoftBreak(_), IterEventType::Enter)
+                | (Node::LineBreak(_), IterEventType::Enter)
+                | (Node::Paragraph(_), IterEventType::Exit)
+                | (Node::Heading(_), IterEventType::Exit) => text.push('\n'),
+                _ => (),
+            }
         }
+
+        Ok(text)
     }
-}
 
-/// Iterator over the subtree rooted in the current node.
-///
-/// NodeIterator is a wrapper around the libcmark iterator and so traverses the subtree using the
-/// same scheme documented [here](https://github.com/commonmark/cmark/blob/a5c83d7a426bda38aac838f9815664f6189d3404/src/cmark.h#L151).
-///
-/// # Examples
-///
-/// Transform all Text Nodes to uppercase
-/// ```
-/// use doogie::{parse_document, Node};
-///
-/// let document = "# My Great Document \
-///     \
-///     * Item 1 \
-///     * Item 2 \
-///     * Item 3";
-///
-/// let root = parse_document(document);
-///
-/// for (mut node, _) in root.iter() {
-///     if let Node::Text(ref mut node) = node {
-///         let content = node.get_content().unwrap();
-///         node.set_content(&content.to_uppercase()).unwrap();
-///     }
-/// }
-/// ```
-///
-/// Remove all level 6 Heading Nodes
-/// ```
-/// use doogie::{parse_document, Node};
-///
-/// let document = "# My Great Document \
-///     \
-///     * Item 1 \
-///     * Item 2 \
-///     * Item 3";
-///
-/// let root = parse_document(document);
-///
-/// for (mut node, _) in root.iter() {
-///     let prune = match node {
-///         Node::Heading(ref heading) => heading.get_level() == 6,
-///         _ => false
-///     };
-///
-///     if prune {
-///         node.unlink();
-///     }
-/// }
-/// ```
-pub struct NodeIterator {
-    /// Raw CMark iterator pointer.
-    pointer: *mut CMarkIterPtr,
-}
+    /// Returns every `Heading` in the subtree rooted at the current `Node` paired with a slug
+    /// derived from its text
+    ///
+    /// Repeated slugs are made unique by appending `-1`, `-2`, etc.
+    fn heading_slugs(&self) -> DoogieResult<Vec<(Node, String)>> {
+        let mut used: HashSet<String> = HashSet::new();
+        let mut slugs = Vec::new();
+
+        for (node, event) in self.iter() {
+            if event != IterEventType::Enter {
+                continue;
+            }
 
-impl NodeIterator {
-    /// Construct a new instance.
-    fn new(node_ptr: *mut CMarkNodePtr) -> NodeIterator {
-        let pointer;
-        unsafe {
-            pointer = cmark_iter_new(node_ptr);
+            if let Node::Heading(_) = node {
+                let base = slugify(&node.to_plain_text()?);
+                let mut slug = base.clone();
+                let mut suffix = 0;
+                while used.contains(&slug) {
+                    suffix += 1;
+                    slug = format!("{}-{}", base, suffix);
+                }
+                used.insert(slug.clone());
+                slugs.push((node, slug));
+            }
         }
 
-        NodeIterator { pointer }
+        Ok(slugs)
     }
-}
 
-impl Iterator for NodeIterator {
-    type Item = (Node, IterEventType);
+    /// Splits every `Paragraph` in the subtree rooted at the current `Node` at its `LineBreak`s
+    ///
+    /// The inline content following each line break is moved, formatting intact, into a new
+    /// `Paragraph` placed after the original one and the line breaks themselves are removed.
+    /// Returns the number of paragraphs created.
+    pub fn hardbreaks_to_paragraphs(&self) -> DoogieResult<usize> {
+        let paragraphs: Vec<Node> = self.iter()
+            .filter(|&(ref node, ref event)| {
+                *event == IterEventType::Enter && match *node {
+                    Node::Paragraph(_) => true,
+                    _ => false,
+                }
+            })
+            .map(|(node, _)| node)
+            .collect();
+        let mut created = 0;
+
+        for paragraph in paragraphs {
+            let children = paragraph.child_nodes()?;
+            let has_break = children.iter().any(|child| match *child {
+                Node::LineBreak(_) => true,
+                _ => false,
+            });
+            if !has_break {
+                continue;
+            }
 
-    /// Advance the iterator.
-    fn next(&mut self) -> Option<Self::Item> {
-        let event_type;
-        unsafe {
-            event_type = IterEventType::try_from(cmark_iter_next(self.pointer) as u32);
-        }
+            let mut parent = match paragraph.parent()? {
+                Some(parent) => parent,
+                None => continue,
+            };
+            let mut following = paragraph.next_sibling()?;
+            let mut current: Option<Node> = None;
+            let mut split = false;
+
+            for mut child in children {
+                if let Node::LineBreak(_) = child {
+                    child.unlink();
+                    split = true;
+                    current = None;
+                    continue;
+                }
 
-        match event_type {
-            Ok(IterEventType::Done) | Ok(IterEventType::None) => None,
-            Ok(event) => {
-                let node_pointer;
-                unsafe {
-                    node_pointer = cmark_iter_get_node(self.pointer);
+                if !split {
+                    continue;
                 }
-                match Node::from_raw(node_pointer) {
-                    Ok(node) => Some((node, event)),
-                    Err(_) => {
-                        error!("Could not instantiate Node from Iterator.");
-                        None
+
+                if current.is_none() {
+                    let mut new_paragraph = Node::from_type(NodeType::CMarkNodeParagraph)?;
+                    match following {
+                        Some(ref mut next) => next.insert_before(&mut new_paragraph)?,
+                        None => parent.append_child(&mut new_paragraph)?,
                     }
+                    created += 1;
+                    current = Some(new_paragraph);
+                }
+
+                if let Some(ref mut target) = current {
+                    target.append_child(&mut child)?;
                 }
             }
-            _ => None,
         }
+
+        Ok(created)
     }
-}
 
-impl Drop for NodeIterator {
-    /// Free the CMark memory allocated for the iterator.
-    fn drop(&mut self) {
-        unsafe {
-            cmark_iter_free(self.pointer);
+    /// Returns the nearest `Heading` preceding the current `Node` in document order
+    ///
+    /// The previous siblings of the current `Node` are searched first, then those of each of its
+    /// ancestors in turn, so that content nested in lists or block quotes is governed by the
+    /// heading of its enclosing section. An enclosing `Heading` is returned directly.
+    pub fn governing_heading(&self) -> DoogieResult<Option<Node>> {
+        let mut current = self.itself()?;
+
+        loop {
+            let mut sibling = current.prev_sibling()?;
+            while let Some(node) = sibling {
+                if let Node::Heading(_) = node {
+                    return Ok(Some(node));
+                }
+                sibling = node.prev_sibling()?;
+            }
+
+            current = match current.parent()? {
+                Some(node @ Node::Heading(_)) => return Ok(Some(node)),
+                Some(parent) => parent,
+                None => return Ok(None),
+            };
         }
     }
-}
 
-/// Manages the memory resources of `Node` instances.
-#[derive(Debug)]
-struct ResourceManager {
-    roots: RefCell<Vec<*mut CMarkNodePtr>>,
-}
+    /// Removes effectively empty blocks from the start and the end of the children of the current
+    /// `Node`, leaving any in between untouched
+    ///
+    /// A block is effectively empty when it holds no images, thematic breaks or non-whitespace
+    /// content. Returns the number of blocks removed.
+    pub fn trim_empty_blocks(&self) -> DoogieResult<usize> {
+        let mut removed = 0;
+
+        while let Some(mut child) = self.first_child()? {
+            if !child.is_empty_block()? {
+                break;
+            }
+            child.unlink();
+            removed += 1;
+        }
 
-impl Drop for ResourceManager {
-    fn drop(&mut self) {
-        let roots = self.roots.borrow();
-        for pointer in roots.iter() {
-            unsafe {
-                cmark_node_free(*pointer);
+        while let Some(mut child) = self.last_child()? {
+            if !child.is_empty_block()? {
+                break;
             }
+            child.unlink();
+            removed += 1;
         }
+
+        Ok(removed)
     }
-}
 
-impl ResourceManager {
-    /// Construct a new ResourceManager instance.
-    pub fn new() -> ResourceManager {
-        ResourceManager {
-            roots: RefCell::new(Vec::new()),
+    /// Determines if the current `Node` is a block without any meaningful content
+    fn is_empty_block(&self) -> DoogieResult<bool> {
+        if !BLOCK_TYPES.contains(&self.get_cmark_type()?) {
+            return Ok(false);
         }
-    }
 
-    /// Tracks the given pointer as a root Node of some tree or subtree
-    pub fn track_root(&self, pointer: &*mut CMarkNodePtr) {
-        let mut roots = self.roots.borrow_mut();
-        if !roots.contains(&pointer) {
-            roots.push(pointer.clone());
+        for (node, event) in self.iter() {
+            if event != IterEventType::Enter {
+                continue;
+            }
+
+            match node {
+                Node::ThematicBreak(_) | Node::Image(_) => return Ok(false),
+                _ => {
+                    if !node.get_literal()?.trim().is_empty() {
+                        return Ok(false);
+                    }
+                }
+            }
         }
-    }
 
-    /// Removes the tracking for a given pointer
-    pub fn untrack_root(&self, pointer: &*mut CMarkNodePtr) {
-        let mut roots = self.roots.borrow_mut();
-        roots.remove_item(pointer);
+        Ok(true)
     }
 
-    #[cfg(test)]
-    /// Determines if the given pointer is currently being tracked
-    pub fn is_tracking(&self, pointer: &*mut CMarkNodePtr) -> bool {
-        let roots = self.roots.borrow();
-        roots.contains(pointer)
+    /// Returns the start line and column of the current `Node`, falling back to those of its
+    /// nearest positioned ancestor when libcmark has not recorded a position for it
+    fn source_position(&self) -> DoogieResult<(u32, u32)> {
+        let mut line = self.get_start_line();
+        let mut column = self.get_start_column();
+        let mut ancestor = self.parent()?;
+
+        while line == 0 {
+            match ancestor {
+                Some(node) => {
+                    line = node.get_start_line();
+                    column = node.get_start_column();
+                    ancestor = node.parent()?;
+                }
+                None => break,
+            }
+        }
+
+        Ok((line, column))
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::{
-        cmark_node_new, parse_document, CMarkNodePtr, CodeBlock, IterEventType, Node, NodeResource,
-        NodeType, Text,
-    };
-    use constants::*;
-    use proptest::prelude::*;
-    use try_from::TryFrom;
+    /// Returns the literal content of the current `Node`, or an empty string if it has none
+    fn get_literal(&self) -> DoogieResult<String> {
+        let result;
+        unsafe {
+            result = cmark_node_get_literal(self.pointer());
+        }
 
-    /// Returns some arbitrary alphanumeric textual content
-    fn arb_content(max_words: usize) -> BoxedStrategy<String> {
-        prop::collection::vec("[[:alnum:]]{1,45}", 1..max_words)
-            .prop_map(|v| v.join(" "))
-            .boxed()
+        literal_to_string(result)
     }
 
-    #[test]
-    fn test_parse_document() {
-        let body = "\
-        # My New Document
-        ";
-        let node = parse_document(body);
-
-        match node {
-            Node::Document(_) => (),
-            _ => panic!("Did not get a Document Node after parsing."),
-        }
+    /// Returns the direct children of the current `Node` in order
+    fn child_nodes(&self) -> DoogieResult<Vec<Node>> {
+        Ok(self.children().collect())
     }
 
-    #[test]
-    fn test_equality() {
-        let body = "\
-        # My New Document
-        ";
-        let node = parse_document(body);
-        let other = node.itself().unwrap();
+    /// Returns the length of the longest line and the average line length of the current `Node`
+    /// rendered in the given format
+    ///
+    /// Lengths are measured in characters. An empty rendering yields `(0, 0.0)`.
+    pub fn rendered_line_stats(&self, format: RenderFormat) -> DoogieResult<(usize, f64)> {
+        let rendered = self.render(format);
+        let lengths: Vec<usize> = rendered.lines().map(|line| line.chars().count()).collect();
 
-        assert_eq!(node, other);
-    }
+        if lengths.is_empty() {
+            return Ok((0, 0.0));
+        }
 
-    #[test]
-    fn test_inequality() {
-        let body = "\
-        # My New Document
-        ";
-        let node = parse_document(body);
-        let other = node.first_child()
-            .unwrap()
-            .expect("Root should have a child");
+        let longest = lengths.iter().cloned().max().unwrap_or(0);
+        let total: usize = lengths.iter().sum();
 
-        assert_ne!(node, other);
+        Ok((longest, total as f64 / lengths.len() as f64))
     }
 
-    #[test]
-    fn test_root_node_gets_tracked() {
-        let body = "\
-        # My New Document
-        ";
-        let manager;
-        let pointer;
-        {
-            let node = parse_document(body);
-            manager = node.manager();
-            pointer = node.pointer();
+    /// Counts the sentences in the subtree rooted at the current `Node`
+    ///
+    /// Each paragraph and heading is segmented separately, so a heading without terminal
+    /// punctuation counts as one sentence. A sentence ends at a word ending in `.`, `!` or `?`,
+    /// ignoring trailing quotes and brackets, except when the word is a common abbreviation such
+    /// as "Dr." or "e.g." or a single-letter initial. Periods inside a word, such as those of
+    /// decimal numbers, never end a sentence. Inline code is included while code blocks are not.
+    pub fn sentence_count(&self) -> DoogieResult<usize> {
+        let mut count = 0;
+
+        for (node, event) in self.iter() {
+            if event != IterEventType::Enter {
+                continue;
+            }
+
+            match node {
+                Node::Paragraph(_) | Node::Heading(_) => {
+                    count += count_sentences(&node.to_plain_text()?);
+                }
+                _ => (),
+            }
         }
-        assert!(manager.roots.borrow().contains(&pointer));
+
+        Ok(count)
     }
 
-    #[test]
-    fn test_iterator_hits_all_items() {
-        let body = "* Item 1\n* Item 2\n* Item 3";
-        let root = parse_document(body);
-        let mut node_contents: Vec<String> = Vec::new();
-        let mut item_count = 0;
+    /// Returns every `Link` in the subtree rooted at the current `Node` that repeats the URL and
+    /// text of the link immediately preceding it
+    ///
+    /// Whitespace-only `Text` and `SoftBreak` siblings between the two links are ignored.
+    pub fn duplicate_adjacent_links(&self) -> DoogieResult<Vec<Node>> {
+        let mut duplicates = Vec::new();
 
-        for item in root.iter() {
-            match item {
-                (Node::Item(_), IterEventType::Enter) => item_count += 1,
-                (Node::Text(ref text), IterEventType::Enter) => {
-                    node_contents.push(text.get_content().unwrap())
+        for (node, event) in self.iter() {
+            if event != IterEventType::Enter {
+                continue;
+            }
+
+            let url = match node {
+                Node::Link(ref link) => link.get_url()?,
+                _ => continue,
+            };
+
+            let mut previous = node.prev_sibling()?;
+            while let Some(sibling) = previous {
+                let skip = match sibling {
+                    Node::Text(ref text) => text.get_content()?.trim().is_empty(),
+                    Node::SoftBreak(_) => true,
+                    _ => false,
+                };
+                if !skip {
+                    previous = Some(sibling);
+                    break;
                 }
-                _ => (),
+                previous = sibling.prev_sibling()?;
+            }
+
+            let previous = match previous {
+                Some(previous) => previous,
+                None => continue,
+            };
+            let duplicate = match previous {
+                Node::Link(ref link) => {
+                    link.get_url()? == url && previous.to_plain_text()? == node.to_plain_text()?
+                }
+                _ => false,
+            };
+
+            if duplicate {
+                duplicates.push(node);
             }
         }
 
-        assert_eq!(item_count, 3);
-        assert!(node_contents.contains(&String::from("Item 1")));
-        assert!(node_contents.contains(&String::from("Item 2")));
-        assert!(node_contents.contains(&String::from("Item 3")));
+        Ok(duplicates)
     }
 
-    #[test]
-    fn test_parent_child_traversal() {
-        let body = "* Item 1\n* Item 2\n* Item 3";
-        let root = parse_document(body);
-        let child = root.first_child()
-            .unwrap()
-            .expect("Root should have had child");
-        assert_eq!(
-            root,
-            child
-                .parent()
-                .unwrap()
-                .expect("Child should have had a parent")
-        );
+    /// Renders the subtree rooted at the current `Node` into textual CommonMark form keeping only
+    /// the blocks whose type is in `keep`
+    ///
+    /// Blocks are removed, together with all of their content, from a copy of the subtree so the
+    /// current `Node` is left unmodified. Inline content of the kept blocks is rendered as is.
+    pub fn render_filtered(&self, keep: &[NodeType]) -> DoogieResult<String> {
+        let copy = self.deep_copy()?;
+        copy.remove_blocks_not_in(keep)?;
+
+        Ok(copy.render_commonmark())
     }
 
-    #[test]
-    fn test_sibling_traversal() {
-        let body = "* Item 1\n* Item 2\n* Item 3";
-        let root = parse_document(body);
-        let list = root.first_child()
-            .unwrap()
-            .expect("Root should have had list");
-        let first_item = list.first_child()
-            .unwrap()
-            .expect("List should have had item");
-        let next_item = first_item
-            .next_sibling()
-            .unwrap()
-            .expect("First item should have had next sibling");
+    /// Recursively unlinks the block descendants of the current `Node` whose type is not in `keep`
+    fn remove_blocks_not_in(&self, keep: &[NodeType]) -> DoogieResult<()> {
+        for mut child in self.child_nodes()? {
+            let node_type = child.get_cmark_type()?;
+            if !BLOCK_TYPES.contains(&node_type) {
+                continue;
+            }
 
-        assert_eq!(
-            first_item,
-            next_item
-                .prev_sibling()
-                .unwrap()
-                .expect("Next item should have had prev item")
-        );
+            if keep.contains(&node_type) {
+                child.remove_blocks_not_in(keep)?;
+            } else {
+                child.unlink();
+            }
+        }
+
+        Ok(())
     }
 
-    #[test]
-    fn parse_and_render() {
-        let content = "# Testing";
-        let root = parse_document(content);
+    /// Returns every `Paragraph` in the subtree rooted at the current `Node` paired with the
+    /// predominant direction of its plain text
+    ///
+    /// A paragraph is right-to-left when it holds more strong right-to-left characters, those of
+    /// the Hebrew and Arabic blocks, than other alphabetic characters. Code is not considered.
+    pub fn paragraph_text_directions(&self) -> DoogieResult<Vec<(Node, TextDirection)>> {
+        let mut directions = Vec::new();
+
+        for (node, event) in self.iter() {
+            if event != IterEventType::Enter {
+                continue;
+            }
 
-        assert_eq!(content, root.render_commonmark().trim());
+            if let Node::Paragraph(_) = node {
+                let text = node.collect_plain_text(false)?;
+                let rtl = text.chars().filter(|&c| is_strong_rtl(c)).count();
+                let ltr = text.chars()
+                    .filter(|&c| c.is_alphabetic() && !is_strong_rtl(c))
+                    .count();
+                let direction = if rtl > ltr {
+                    TextDirection::RightToLeft
+                } else {
+                    TextDirection::LeftToRight
+                };
+                directions.push((node, direction));
+            }
+        }
+
+        Ok(directions)
     }
 
-    #[test]
-    fn test_from_raw() {
-        let node_pointer: *mut CMarkNodePtr;
-        unsafe {
-            node_pointer = cmark_node_new(NodeType::CMarkNodeParagraph as u32);
-        }
+    /// Merges the `Paragraph` following the current `Paragraph` into it
+    ///
+    /// The inline children of the following paragraph are appended to the current one after a
+    /// `Text` holding `separator`, and the emptied paragraph is unlinked. Returns `false` without
+    /// modifying the tree when the current `Node` or its next sibling is not a `Paragraph`.
+    pub fn merge_with_next_paragraph(&self, separator: &str) -> DoogieResult<bool> {
+        let mut current = match *self {
+            Node::Paragraph(_) => self.itself()?,
+            _ => return Ok(false),
+        };
+        let mut next = match self.next_sibling()? {
+            Some(next @ Node::Paragraph(_)) => next,
+            _ => return Ok(false),
+        };
 
-        let node = Node::from_raw(node_pointer).unwrap();
+        if !separator.is_empty() {
+            let mut text = Node::from_type(NodeType::CMarkNodeText)?;
+            if let Node::Text(ref mut text) = text {
+                text.set_content(&separator.to_string())?;
+            }
+            current.append_child(&mut text)?;
+        }
 
-        match node {
-            Node::Paragraph(_) => (),
-            _ => panic!("Node should have been a paragraph"),
+        for mut child in next.child_nodes()? {
+            current.append_child(&mut child)?;
         }
+        next.unlink();
+
+        Ok(true)
     }
 
-    #[test]
-    fn test_unlink() {
-        let body = "* Item 1\n* Item 2\n* Item 3";
-        let root = parse_document(body);
-        let mut first_item = root.first_child()
-            .unwrap()
-            .expect("Root should have first child")
-            .first_child()
-            .unwrap()
-            .expect("List should have first item");
-        let manager = first_item.manager();
+    /// Returns the largest number of `List`s enclosing any `Item` in the subtree rooted at the
+    /// current `Node`
+    ///
+    /// Only lists within the subtree are counted. Returns 0 when the subtree holds no items.
+    pub fn max_list_nesting(&self) -> DoogieResult<usize> {
+        let mut depth: usize = 0;
+        let mut deepest = 0;
+
+        for (node_type, event) in self.iter_types() {
+            match (node_type, event) {
+                (NodeType::CMarkNodeList, IterEventType::Enter) => depth += 1,
+                (NodeType::CMarkNodeList, IterEventType::Exit) => depth = depth.saturating_sub(1),
+                (NodeType::CMarkNodeItem, IterEventType::Enter) => deepest = deepest.max(depth),
+                _ => (),
+            }
+        }
 
-        first_item.unlink();
+        Ok(deepest)
+    }
 
-        assert!(manager.roots.borrow().contains(&first_item.pointer()));
-        for (node, _) in root.iter() {
-            if let Node::Text(node) = node {
-                assert!(!node.get_content().unwrap().contains("Item 1"));
+    /// Returns every fenced `CodeBlock` in the subtree rooted at the current `Node` that does not
+    /// declare a language
+    ///
+    /// Indented code blocks cannot declare a language and are excluded, which is determined from
+    /// `source`, the text the document was parsed from, as with `CodeBlock::is_fenced`.
+    pub fn code_blocks_missing_language(&self, source: &str) -> DoogieResult<Vec<Node>> {
+        let mut missing = Vec::new();
+
+        for (node, event) in self.iter() {
+            if event != IterEventType::Enter {
+                continue;
+            }
+
+            let unlabeled = match node {
+                Node::CodeBlock(ref block) => {
+                    block.is_fenced(source)? && block.fence_attributes()?.language.is_none()
+                }
+                _ => false,
+            };
+            if unlabeled {
+                missing.push(node);
             }
         }
+
+        Ok(missing)
     }
 
-    #[test]
-    fn test_append_child() {
-        let mut root_node = Node::from_type(NodeType::CMarkNodeDocument).unwrap();
-        let mut child_node = Node::from_type(NodeType::CMarkNodeParagraph).unwrap();
+    /// Determines if the current `Node` is a proper ancestor of the given `Node`
+    ///
+    /// A `Node` is not an ancestor of itself.
+    pub fn is_ancestor_of(&self, other: &Node) -> DoogieResult<bool> {
+        let mut ancestor = other.parent()?;
 
-        root_node.append_child(&mut child_node).unwrap();
+        while let Some(node) = ancestor {
+            if node == *self {
+                return Ok(true);
+            }
+            ancestor = node.parent()?;
+        }
 
-        assert!(!root_node.manager().is_tracking(&child_node.pointer()));
-        assert_eq!(
-            root_node
-                .first_child()
-                .unwrap()
-                .expect("Root should have child"),
-            child_node
-        );
+        Ok(false)
     }
 
-    #[test]
-    fn test_document_children() {
-        for i in 1..21 {
-            let node_type = NodeType::CMarkNodeDocument;
-            let other_type = NodeType::try_from(i).unwrap();
-            let mut node = Node::from_type(node_type).unwrap();
-            let mut child = Node::from_type(other_type.clone()).unwrap();
-            match node.can_append_child(&child).unwrap() {
-                true => assert!(
-                    DOCUMENT_CHILDREN.contains(&other_type),
-                    "{:?} should not have been a valid block quote child, but was",
-                    other_type
-                ),
-                false => assert!(
-                    !DOCUMENT_CHILDREN.contains(&other_type),
-                    "{:?} should be a valid block quote child, but was not",
-                    other_type
-                ),
+    /// Replaces every `List` in the subtree rooted at the current `Node` whose single `Item`
+    /// holds nothing but another `List` with that inner list
+    ///
+    /// Chains of such lists are collapsed down to the innermost one. Returns the number of lists
+    /// removed.
+    pub fn collapse_single_item_lists(&self) -> DoogieResult<usize> {
+        let lists: Vec<Node> = self.iter()
+            .filter(|&(ref node, ref event)| {
+                *event == IterEventType::Enter && match *node {
+                    Node::List(_) => true,
+                    _ => false,
+                }
+            })
+            .map(|(node, _)| node)
+            .collect();
+        let mut collapsed = 0;
+
+        for mut list in lists {
+            let items = list.child_nodes()?;
+            if items.len() != 1 {
+                continue;
             }
-            match node.append_child(&mut child) {
-                Err(_) => assert!(
-                    !DOCUMENT_CHILDREN.contains(&other_type),
-                    "{:?} should be able to append, but was not",
-                    other_type
-                ),
-                Ok(_) => assert!(
-                    DOCUMENT_CHILDREN.contains(&other_type),
-                    "{:?} should not have been able to append, but was",
-                    other_type
-                ),
+
+            let mut content = items[0].child_nodes()?;
+            if content.len() != 1 {
+                continue;
+            }
+
+            let mut inner = content.remove(0);
+            if let Node::List(_) = inner {
+                list.replace_with(&mut inner)?;
+                collapsed += 1;
             }
         }
+
+        Ok(collapsed)
     }
 
-    #[test]
-    fn test_block_quote_children() {
-        for i in 1..21 {
-            let node_type = NodeType::CMarkNodeBlockQuote;
-            let other_type = NodeType::try_from(i).unwrap();
-            let mut node = Node::from_type(node_type).unwrap();
-            let mut child = Node::from_type(other_type.clone()).unwrap();
-            match node.can_append_child(&child).unwrap() {
-                true => assert!(
-                    BLOCK_QUOTE_CHILDREN.contains(&other_type),
-                    "{:?} should not have been a valid block quote child, but was",
-                    other_type
-                ),
-                false => assert!(
-                    !BLOCK_QUOTE_CHILDREN.contains(&other_type),
-                    "{:?} should be a valid block quote child, but was not",
-                    other_type
-                ),
+    /// Returns every inline `Code` in the subtree rooted at the current `Node` whose content is a
+    /// bare URL, which was likely meant to be a link
+    pub fn code_spans_looking_like_urls(&self) -> DoogieResult<Vec<Node>> {
+        let mut spans = Vec::new();
+
+        for (node, event) in self.iter() {
+            if event != IterEventType::Enter {
+                continue;
             }
-            match node.append_child(&mut child) {
-                Err(_) => assert!(
-                    !BLOCK_QUOTE_CHILDREN.contains(&other_type),
-                    "{:?} should be able to append, but was not",
-                    other_type
-                ),
-                Ok(_) => assert!(
-                    BLOCK_QUOTE_CHILDREN.contains(&other_type),
-                    "{:?} should not have been able to append, but was",
-                    other_type
-                ),
+
+            let url_like = match node {
+                Node::Code(ref code) => looks_like_url(code.get_content()?.trim()),
+                _ => false,
+            };
+            if url_like {
+                spans.push(node);
             }
         }
+
+        Ok(spans)
     }
 
-    #[test]
-    fn test_list_children() {
-        for i in 1..21 {
-            let node_type = NodeType::CMarkNodeList;
-            let other_type = NodeType::try_from(i).unwrap();
-            let mut node = Node::from_type(node_type).unwrap();
-            let mut child = Node::from_type(other_type.clone()).unwrap();
-            match node.can_append_child(&child).unwrap() {
-                true => assert!(
-                    LIST_CHILDREN.contains(&other_type),
-                    "{:?} should not have been a valid block quote child, but was",
-                    other_type
-                ),
-                false => assert!(
-                    !LIST_CHILDREN.contains(&other_type),
-                    "{:?} should be a valid block quote child, but was not",
-                    other_type
-                ),
+    /// Returns the title of the subtree rooted at the current `Node`, the plain text of its first
+    /// level 1 `Heading`
+    pub fn title(&self) -> DoogieResult<Option<String>> {
+        Ok(match self.title_heading()? {
+            Some(heading) => Some(heading.to_plain_text()?.trim().to_string()),
+            None => None,
+        })
+    }
+
+    /// Returns the first level 1 `Heading` in the subtree rooted at the current `Node`
+    fn title_heading(&self) -> DoogieResult<Option<Node>> {
+        for (node, event) in self.iter() {
+            if event != IterEventType::Enter {
+                continue;
             }
-            match node.append_child(&mut child) {
-                Err(_) => assert!(
-                    !LIST_CHILDREN.contains(&other_type),
-                    "{:?} should be able to append, but was not",
-                    other_type
-                ),
-                Ok(_) => assert!(
-                    LIST_CHILDREN.contains(&other_type),
-                    "{:?} should not have been able to append, but was",
-                    other_type
-                ),
+
+            if let Node::Heading(ref heading) = node {
+                if heading.get_level() == 1 {
+                    return Ok(Some(node.itself()?));
+                }
             }
         }
+
+        Ok(None)
     }
 
-    #[test]
-    fn test_item_children() {
-        for i in 1..21 {
-            let node_type = NodeType::CMarkNodeItem;
-            let other_type = NodeType::try_from(i).unwrap();
-            let mut node = Node::from_type(node_type).unwrap();
-            let mut child = Node::from_type(other_type.clone()).unwrap();
-            match node.can_append_child(&child).unwrap() {
-                true => assert!(
-                    ITEM_CHILDREN.contains(&other_type),
-                    "{:?} should not have been a valid block quote child, but was",
-                    other_type
-                ),
-                false => assert!(
-                    !ITEM_CHILDREN.contains(&other_type),
-                    "{:?} should be a valid block quote child, but was not",
-                    other_type
-                ),
+    /// Returns every `Heading` other than the title heading in the subtree rooted at the current
+    /// `Node` whose text repeats the title, ignoring case
+    pub fn headings_matching_title(&self) -> DoogieResult<Vec<Node>> {
+        let title_heading = match self.title_heading()? {
+            Some(heading) => heading,
+            None => return Ok(Vec::new()),
+        };
+        let title = title_heading.to_plain_text()?.trim().to_lowercase();
+        let mut matching = Vec::new();
+
+        for (node, event) in self.iter() {
+            if event != IterEventType::Enter || node == title_heading {
+                continue;
             }
-            match node.append_child(&mut child) {
-                Err(_) => assert!(
-                    !ITEM_CHILDREN.contains(&other_type),
-                    "{:?} should be able to append, but was not",
-                    other_type
-                ),
-                Ok(_) => assert!(
-                    ITEM_CHILDREN.contains(&other_type),
-                    "{:?} should not have been able to append, but was",
-                    other_type
-                ),
+
+            if let Node::Heading(_) = node {
+                if node.to_plain_text()?.trim().to_lowercase() == title {
+                    matching.push(node);
+                }
             }
         }
+
+        Ok(matching)
     }
 
-    #[test]
-    fn test_code_block_children() {
-        for i in 1..21 {
-            let node_type = NodeType::CMarkNodeCodeBlock;
-            let other_type = NodeType::try_from(i).unwrap();
-            let mut node = Node::from_type(node_type).unwrap();
-            let mut child = Node::from_type(other_type.clone()).unwrap();
-            match node.can_append_child(&child).unwrap() {
-                true => assert!(
-                    CODE_BLOCK_CHILDREN.contains(&other_type),
-                    "{:?} should not have been a valid block quote child, but was",
-                    other_type
-                ),
-                false => assert!(
-                    !CODE_BLOCK_CHILDREN.contains(&other_type),
-                    "{:?} should be a valid block quote child, but was not",
-                    other_type
-                ),
-            }
-            match node.append_child(&mut child) {
-                Err(_) => assert!(
-                    !CODE_BLOCK_CHILDREN.contains(&other_type),
-                    "{:?} should be able to append, but was not",
-                    other_type
-                ),
-                Ok(_) => assert!(
-                    CODE_BLOCK_CHILDREN.contains(&other_type),
-                    "{:?} should not have been able to append, but was",
-                    other_type
-                ),
-            }
+    /// Returns the fraction of the visible characters of the subtree rooted at the current `Node`
+    /// that belong to `CodeBlock`s and inline `Code`
+    ///
+    /// Whitespace is not counted. Returns 0 when there are no visible characters.
+    pub fn code_to_prose_ratio(&self) -> DoogieResult<f64> {
+        let visible = |text: String| text.chars().filter(|c| !c.is_whitespace()).count();
+        let total = visible(self.collect_plain_text(true)?);
+        let prose = visible(self.collect_plain_text(false)?);
+
+        if total == 0 {
+            return Ok(0.0);
         }
+
+        Ok((total - prose) as f64 / total as f64)
     }
 
-    #[test]
-    fn test_html_block_children() {
-        for i in 1..21 {
-            let node_type = NodeType::CMarkNodeHtmlBlock;
-            let other_type = NodeType::try_from(i).unwrap();
-            let mut node = Node::from_type(node_type).unwrap();
-            let mut child = Node::from_type(other_type.clone()).unwrap();
-            match node.can_append_child(&child).unwrap() {
-                true => assert!(
-                    HTML_BLOCK_CHILDREN.contains(&other_type),
-                    "{:?} should not have been a valid block quote child, but was",
-                    other_type
-                ),
-                false => assert!(
-                    !HTML_BLOCK_CHILDREN.contains(&other_type),
-                    "{:?} should be a valid block quote child, but was not",
-                    other_type
-                ),
-            }
-            match node.append_child(&mut child) {
-                Err(_) => assert!(
-                    !HTML_BLOCK_CHILDREN.contains(&other_type),
-                    "{:?} should be able to append, but was not",
-                    other_type
-                ),
-                Ok(_) => assert!(
-                    HTML_BLOCK_CHILDREN.contains(&other_type),
-                    "{:?} should not have been able to append, but was",
-                    other_type
-                ),
+    /// Returns the nearest ancestor of the current `Node` of the given type, if any
+    pub fn ancestor_of_type(&self, node_type: NodeType) -> DoogieResult<Option<Node>> {
+        for ancestor in self.ancestors() {
+            let ancestor = ancestor?;
+            if ancestor.get_cmark_type()? == node_type {
+                return Ok(Some(ancestor));
             }
         }
+
+        Ok(None)
     }
 
-    #[test]
-    fn test_custom_block_children() {
-        for i in 1..21 {
-            let node_type = NodeType::CMarkNodeCustomBlock;
-            let other_type = NodeType::try_from(i).unwrap();
-            let mut node = Node::from_type(node_type).unwrap();
-            let mut child = Node::from_type(other_type.clone()).unwrap();
-            match node.can_append_child(&child).unwrap() {
-                true => assert!(
-                    CUSTOM_BLOCK_CHILDREN.contains(&other_type),
-                    "{:?} should not have been a valid block quote child, but was",
-                    other_type
-                ),
-                false => assert!(
-                    !CUSTOM_BLOCK_CHILDREN.contains(&other_type),
-                    "{:?} should be a valid block quote child, but was not",
-                    other_type
-                ),
+    /// Returns the nearest `List` enclosing the current `Node`, if any
+    pub fn enclosing_list(&self) -> DoogieResult<Option<Node>> {
+        self.ancestor_of_type(NodeType::CMarkNodeList)
+    }
+
+    /// Returns the nearest `Item` enclosing the current `Node`, if any
+    pub fn enclosing_item(&self) -> DoogieResult<Option<Node>> {
+        self.ancestor_of_type(NodeType::CMarkNodeItem)
+    }
+
+    /// Counts the words in the subtree rooted at the current `Node`, including those of code
+    ///
+    /// A word is a run of non-whitespace characters containing at least one alphanumeric
+    /// character.
+    pub fn word_count(&self) -> DoogieResult<usize> {
+        Ok(count_words(&self.collect_plain_text(true)?))
+    }
+
+    /// Estimates the number of minutes needed to read the subtree rooted at the current `Node`
+    /// at the given reading speed
+    ///
+    /// A reading speed of 0 yields 0.
+    pub fn reading_time_minutes(&self, words_per_minute: usize) -> DoogieResult<f64> {
+        self.reading_time_minutes_weighted(words_per_minute, 1.0)
+    }
+
+    /// Estimates the number of minutes needed to read the subtree rooted at the current `Node`
+    /// at the given reading speed, counting each word of code `code_multiplier` times
+    ///
+    /// A reading speed of 0 yields 0.
+    pub fn reading_time_minutes_weighted(
+        &self,
+        words_per_minute: usize,
+        code_multiplier: f64,
+    ) -> DoogieResult<f64> {
+        if words_per_minute == 0 {
+            return Ok(0.0);
+        }
+
+        let total = self.word_count()?;
+        let prose = count_words(&self.collect_plain_text(false)?);
+        let weighted = prose as f64 + (total - prose) as f64 * code_multiplier;
+
+        Ok(weighted / words_per_minute as f64)
+    }
+
+    /// Returns every `Image` in the subtree rooted at the current `Node`, paired with its URL,
+    /// whose file extension is not one of the allowed ones
+    ///
+    /// The extension is taken from the last segment of the URL path, ignoring any query or
+    /// fragment, and compared case-insensitively. Allowed extensions may be given with or without
+    /// a leading `.`. Images without an extension are reported as well.
+    pub fn images_with_disallowed_extensions(
+        &self,
+        allowed: &[&str],
+    ) -> DoogieResult<Vec<(Node, String)>> {
+        let allowed: Vec<String> = allowed
+            .iter()
+            .map(|extension| extension.trim_start_matches('.').to_lowercase())
+            .collect();
+        let mut disallowed = Vec::new();
+
+        for (node, event) in self.iter() {
+            if event != IterEventType::Enter {
+                continue;
             }
-            match node.append_child(&mut child) {
-                Err(_) => assert!(
-                    !CUSTOM_BLOCK_CHILDREN.contains(&other_type),
-                    "{:?} should be able to append, but was not",
-                    other_type
-                ),
-                Ok(_) => assert!(
-                    CUSTOM_BLOCK_CHILDREN.contains(&other_type),
-                    "{:?} should not have been able to append, but was",
-                    other_type
-                ),
+
+            let url = match node {
+                Node::Image(ref image) => image.get_url()?,
+                _ => continue,
+            };
+
+            let extension = url_extension(&url).to_lowercase();
+            if !allowed.contains(&extension) {
+                disallowed.push((node, url));
             }
         }
+
+        Ok(disallowed)
+    }
+
+    /// Splits the current `Text` at the given byte offset
+    ///
+    /// The current `Text` keeps the content before the offset and a new `Text` holding the rest
+    /// is inserted after it. Both are returned in order. The offset must fall on a character
+    /// boundary of the content.
+    pub fn split_text_at(&self, offset: usize) -> DoogieResult<(Node, Node)> {
+        let mut first = self.itself()?;
+        let content = match first {
+            Node::Text(ref text) => text.get_content()?,
+            _ => return Err(DoogieError::UnsupportedNodeType(self.get_cmark_type()?)),
+        };
+
+        if !content.is_char_boundary(offset) {
+            return Err(DoogieError::InvalidOffset(offset));
+        }
+
+        let mut second = Node::from_type(NodeType::CMarkNodeText)?;
+        if let Node::Text(ref mut text) = second {
+            text.set_content(&content[offset..].to_string())?;
+        }
+        if let Node::Text(ref mut text) = first {
+            text.set_content(&content[..offset].to_string())?;
+        }
+        first.insert_after(&mut second)?;
+
+        Ok((first, second))
     }
 
-    #[test]
-    fn test_paragraph_children() {
-        for i in 1..21 {
-            let node_type = NodeType::CMarkNodeParagraph;
-            let other_type = NodeType::try_from(i).unwrap();
-            let mut node = Node::from_type(node_type).unwrap();
-            let mut child = Node::from_type(other_type.clone()).unwrap();
-            match node.can_append_child(&child).unwrap() {
-                true => assert!(
-                    PARAGRAPH_CHILDREN.contains(&other_type),
-                    "{:?} should not have been a valid block quote child, but was",
-                    other_type
-                ),
-                false => assert!(
-                    !PARAGRAPH_CHILDREN.contains(&other_type),
-                    "{:?} should be a valid block quote child, but was not",
-                    other_type
-                ),
+    /// Returns the `CodeBlock` with the most lines in the subtree rooted at the current `Node`
+    /// along with its line count
+    ///
+    /// The first of several equally long blocks is returned.
+    pub fn longest_code_block(&self) -> DoogieResult<Option<(Node, usize)>> {
+        let mut longest: Option<(Node, usize)> = None;
+
+        for (node, event) in self.iter() {
+            if event != IterEventType::Enter {
+                continue;
             }
-            match node.append_child(&mut child) {
-                Err(_) => assert!(
-                    !PARAGRAPH_CHILDREN.contains(&other_type),
-                    "{:?} should be able to append, but was not",
-                    other_type
-                ),
-                Ok(_) => assert!(
-                    PARAGRAPH_CHILDREN.contains(&other_type),
-                    "{:?} should not have been able to append, but was",
-                    other_type
-                ),
+
+            let lines = match node {
+                Node::CodeBlock(ref block) => block.get_content()?.lines().count(),
+                _ => continue,
+            };
+            let is_longer = match longest {
+                Some((_, most)) => lines > most,
+                None => true,
+            };
+            if is_longer {
+                longest = Some((node, lines));
             }
         }
+
+        Ok(longest)
     }
 
-    #[test]
-    fn test_heading_children() {
-        for i in 1..21 {
-            let node_type = NodeType::CMarkNodeHeading;
-            let other_type = NodeType::try_from(i).unwrap();
-            let mut node = Node::from_type(node_type).unwrap();
-            let mut child = Node::from_type(other_type.clone()).unwrap();
-            match node.can_append_child(&child).unwrap() {
-                true => assert!(
-                    HEADING_CHILDREN.contains(&other_type),
-                    "{:?} should not have been a valid block quote child, but was",
-                    other_type
-                ),
-                false => assert!(
-                    !HEADING_CHILDREN.contains(&other_type),
-                    "{:?} should be a valid block quote child, but was not",
-                    other_type
-                ),
+    /// Repairs every `Link` in the subtree rooted at the current `Node` that has no visible text
+    ///
+    /// A link is considered empty when it holds no images and only whitespace text. Depending on
+    /// `fallback`, its content is replaced with a `Text` holding its URL or the link is unwrapped,
+    /// its content being moved in its place. Returns the number of links repaired.
+    pub fn fix_empty_link_text(&self, fallback: FallbackText) -> DoogieResult<usize> {
+        let mut empty_links = Vec::new();
+
+        for (node, event) in self.iter() {
+            if event != IterEventType::Enter {
+                continue;
             }
-            match node.append_child(&mut child) {
-                Err(_) => assert!(
-                    !HEADING_CHILDREN.contains(&other_type),
-                    "{:?} should be able to append, but was not",
-                    other_type
-                ),
-                Ok(_) => assert!(
-                    HEADING_CHILDREN.contains(&other_type),
-                    "{:?} should not have been able to append, but was",
-                    other_type
-                ),
+
+            if let Node::Link(_) = node {
+                let has_image = node.count(|node_type| node_type == NodeType::CMarkNodeImage)? > 0;
+                if !has_image && node.to_plain_text()?.trim().is_empty() {
+                    empty_links.push(node);
+                }
+            }
+        }
+
+        let fixed = empty_links.len();
+        for mut link in empty_links {
+            match fallback {
+                FallbackText::UseUrl => {
+                    let url = match link {
+                        Node::Link(ref link) => link.get_url()?,
+                        _ => continue,
+                    };
+                    for mut child in link.child_nodes()? {
+                        child.unlink();
+                    }
+
+                    let mut text = Node::from_type(NodeType::CMarkNodeText)?;
+                    if let Node::Text(ref mut text) = text {
+                        text.set_content(&url)?;
+                    }
+                    link.append_child(&mut text)?;
+                }
+                FallbackText::Remove => {
+                    for mut child in link.child_nodes()? {
+                        link.insert_before(&mut child)?;
+                    }
+                    link.unlink();
+                }
             }
         }
+
+        Ok(fixed)
     }
 
-    #[test]
-    fn test_thematic_break_children() {
-        for i in 1..21 {
-            let node_type = NodeType::CMarkNodeThematicBreak;
-            let other_type = NodeType::try_from(i).unwrap();
-            let mut node = Node::from_type(node_type).unwrap();
-            let mut child = Node::from_type(other_type.clone()).unwrap();
-            match node.can_append_child(&child).unwrap() {
-                true => assert!(
-                    THEMATIC_BREAK_CHILDREN.contains(&other_type),
-                    "{:?} should not have been a valid block quote child, but was",
-                    other_type
-                ),
-                false => assert!(
-                    !THEMATIC_BREAK_CHILDREN.contains(&other_type),
-                    "{:?} should be a valid block quote child, but was not",
-                    other_type
-                ),
+    /// Returns every pair of `Heading`s in the subtree rooted at the current `Node` where the
+    /// second immediately follows the first with no block in between
+    pub fn adjacent_heading_pairs(&self) -> DoogieResult<Vec<(Node, Node)>> {
+        let mut pairs = Vec::new();
+
+        for (node, event) in self.iter() {
+            if event != IterEventType::Enter {
+                continue;
             }
-            match node.append_child(&mut child) {
-                Err(_) => assert!(
-                    !THEMATIC_BREAK_CHILDREN.contains(&other_type),
-                    "{:?} should be able to append, but was not",
-                    other_type
-                ),
-                Ok(_) => assert!(
-                    THEMATIC_BREAK_CHILDREN.contains(&other_type),
-                    "{:?} should not have been able to append, but was",
-                    other_type
-                ),
+
+            if let Node::Heading(_) = node {
+                if let Some(next @ Node::Heading(_)) = node.next_sibling()? {
+                    pairs.push((node.itself()?, next));
+                }
             }
         }
+
+        Ok(pairs)
     }
 
-    #[test]
-    fn test_text_children() {
-        for i in 1..21 {
-            let node_type = NodeType::CMarkNodeText;
-            let other_type = NodeType::try_from(i).unwrap();
-            let mut node = Node::from_type(node_type).unwrap();
-            let mut child = Node::from_type(other_type.clone()).unwrap();
-            match node.can_append_child(&child).unwrap() {
-                true => assert!(
-                    TEXT_CHILDREN.contains(&other_type),
-                    "{:?} should not have been a valid block quote child, but was",
-                    other_type
-                ),
-                false => assert!(
-                    !TEXT_CHILDREN.contains(&other_type),
-                    "{:?} should be a valid block quote child, but was not",
-                    other_type
-                ),
-            }
-            match node.append_child(&mut child) {
-                Err(_) => assert!(
-                    !TEXT_CHILDREN.contains(&other_type),
-                    "{:?} should be able to append, but was not",
-                    other_type
-                ),
-                Ok(_) => assert!(
-                    TEXT_CHILDREN.contains(&other_type),
-                    "{:?} should not have been able to append, but was",
-                    other_type
-                ),
+    /// Walks the subtree rooted at the current `Node` in document order, calling the method of the
+    /// visitor matching the type of each `Node` entered or exited
+    pub fn accept(&self, visitor: &mut dyn Visitor) {
+        for (node, event) in self.iter() {
+            match node {
+                Node::Document(_) => visitor.visit_document(&node, &event),
+                Node::BlockQuote(_) => visitor.visit_block_quote(&node, &event),
+                Node::List(_) => visitor.visit_list(&node, &event),
+                Node::Item(_) => visitor.visit_item(&node, &event),
+                Node::CodeBlock(_) => visitor.visit_code_block(&node, &event),
+                Node::HtmlBlock(_) => visitor.visit_html_block(&node, &event),
+                Node::CustomBlock(_) => visitor.visit_custom_block(&node, &event),
+                Node::Paragraph(_) => visitor.visit_paragraph(&node, &event),
+                Node::Heading(_) => visitor.visit_heading(&node, &event),
+                Node::ThematicBreak(_) => visitor.visit_thematic_break(&node, &event),
+                Node::Text(_) => visitor.visit_text(&node, &event),
+                Node::SoftBreak(_) => visitor.visit_soft_break(&node, &event),
+                Node::LineBreak(_) => visitor.visit_line_break(&node, &event),
+                Node::Code(_) => visitor.visit_code(&node, &event),
+                Node::HtmlInline(_) => visitor.visit_html_inline(&node, &event),
+                Node::CustomInline(_) => visitor.visit_custom_inline(&node, &event),
+                Node::Emph(_) => visitor.visit_emph(&node, &event),
+                Node::Strong(_) => visitor.visit_strong(&node, &event),
+                Node::Link(_) => visitor.visit_link(&node, &event),
+                Node::Image(_) => visitor.visit_image(&node, &event),
             }
         }
     }
+}
 
-    #[test]
-    fn test_soft_break_children() {
-        for i in 1..21 {
-            let node_type = NodeType::CMarkNodeSoftbreak;
-            let other_type = NodeType::try_from(i).unwrap();
-            let mut node = Node::from_type(node_type).unwrap();
-            let mut child = Node::from_type(other_type.clone()).unwrap();
-            match node.can_append_child(&child).unwrap() {
-                true => assert!(
-                    SOFT_BREAK_CHILDREN.contains(&other_type),
-                    "{:?} should not have been a valid block quote child, but was",
-                    other_type
-                ),
-                false => assert!(
-                    !SOFT_BREAK_CHILDREN.contains(&other_type),
-                    "{:?} should be a valid block quote child, but was not",
-                    other_type
-                ),
-            }
-            match node.append_child(&mut child) {
-                Err(_) => assert!(
-                    !SOFT_BREAK_CHILDREN.contains(&other_type),
-                    "{:?} should be able to append, but was not",
-                    other_type
-                ),
-                Ok(_) => assert!(
-                    SOFT_BREAK_CHILDREN.contains(&other_type),
-                    "{:?} should not have been able to append, but was",
-                    other_type
-                ),
-            }
+/// Represents the root `Node` of a document in the CommonMark AST
+pub struct Document {
+    resource: Resource,
+}
+
+impl Document {
+    /// Constructs a new `Document`
+    pub fn new() -> Self {
+        Self {
+            resource: Resource::from_node_type(
+                NodeType::CMarkNodeDocument,
+                Rc::new(ResourceManager::new()),
+            ),
         }
     }
 
-    #[test]
-    fn test_line_break_children() {
-        for i in 1..21 {
-            let node_type = NodeType::CMarkNodeLinebreak;
-            let other_type = NodeType::try_from(i).unwrap();
-            let mut node = Node::from_type(node_type).unwrap();
-            let mut child = Node::from_type(other_type.clone()).unwrap();
-            match node.can_append_child(&child).unwrap() {
-                true => assert!(
-                    LINE_BREAK_CHILDREN.contains(&other_type),
-                    "{:?} should not have been a valid block quote child, but was",
-                    other_type
-                ),
-                false => assert!(
-                    !LINE_BREAK_CHILDREN.contains(&other_type),
-                    "{:?} should be a valid block quote child, but was not",
-                    other_type
-                ),
-            }
-            match node.append_child(&mut child) {
-                Err(_) => assert!(
-                    !LINE_BREAK_CHILDREN.contains(&other_type),
-                    "{:?} should be able to append, but was not",
-                    other_type
-                ),
-                Ok(_) => assert!(
-                    LINE_BREAK_CHILDREN.contains(&other_type),
-                    "{:?} should not have been able to append, but was",
-                    other_type
-                ),
-            }
+    /// Consolidates all adjacent `Text` `Node`s in the document into single `Text` `Node`s.
+    pub fn consolidate_text_nodes(&mut self) {
+        unsafe {
+            cmark_consolidate_text_nodes(self.resource.pointer);
         }
     }
 
-    #[test]
-    fn test_code_children() {
-        for i in 1..21 {
-            let node_type = NodeType::CMarkNodeCode;
-            let other_type = NodeType::try_from(i).unwrap();
-            let mut node = Node::from_type(node_type).unwrap();
-            let mut child = Node::from_type(other_type.clone()).unwrap();
-            match node.can_append_child(&child).unwrap() {
-                true => assert!(
-                    CODE_CHILDREN.contains(&other_type),
-                    "{:?} should not have been a valid block quote child, but was",
-                    other_type
-                ),
-                false => assert!(
-                    !CODE_CHILDREN.contains(&other_type),
-                    "{:?} should be a valid block quote child, but was not",
-                    other_type
-                ),
+    /// Checks that the textual content of every node in the document is valid UTF-8
+    ///
+    /// Returns `DoogieError::InvalidUtf8` carrying the source position of the first offending
+    /// node, so that callers can fail fast instead of when the node is eventually accessed.
+    pub fn validate_content_utf8(&self) -> DoogieResult<()> {
+        let root = Node::from_raw(self.resource.pointer)?;
+
+        for (node, event) in root.iter() {
+            if event != IterEventType::Enter {
+                continue;
             }
-            match node.append_child(&mut child) {
-                Err(_) => assert!(
-                    !CODE_CHILDREN.contains(&other_type),
-                    "{:?} should be able to append, but was not",
-                    other_type
-                ),
-                Ok(_) => assert!(
-                    CODE_CHILDREN.contains(&other_type),
-                    "{:?} should not have been able to append, but was",
-                    other_type
-                ),
+
+            let checked = match node {
+                Node::Link(ref link) => link.get_url().and(link.get_title()),
+                Node::Image(ref image) => image.get_url().and(image.get_title()),
+                Node::CodeBlock(ref block) => block.get_fence_info().and(block.get_content()),
+                _ => node.get_literal(),
+            };
+
+            match checked {
+                Err(DoogieError::Utf8Error(err)) => {
+                    let (line, column) = node.source_position()?;
+                    return Err(DoogieError::InvalidUtf8 { line, column, err });
+                }
+                Err(err) => return Err(err),
+                Ok(_) => (),
             }
         }
+
+        Ok(())
     }
 
-    #[test]
-    fn test_inline_html_children() {
-        for i in 1..21 {
-            let node_type = NodeType::CMarkNodeHtmlInline;
-            let other_type = NodeType::try_from(i).unwrap();
-            let mut node = Node::from_type(node_type).unwrap();
-            let mut child = Node::from_type(other_type.clone()).unwrap();
-            match node.can_append_child(&child).unwrap() {
-                true => assert!(
-                    INLINE_HTML_CHILDREN.contains(&other_type),
-                    "{:?} should not have been a valid block quote child, but was",
-                    other_type
-                ),
-                false => assert!(
-                    !INLINE_HTML_CHILDREN.contains(&other_type),
-                    "{:?} should be a valid block quote child, but was not",
-                    other_type
-                ),
+    /// Demotes every level 1 `Heading` after the first one in the document to level 2
+    ///
+    /// Returns the number of headings that were demoted.
+    pub fn enforce_single_h1(&self) -> DoogieResult<usize> {
+        let root = Node::from_raw(self.resource.pointer)?;
+        let mut seen_first = false;
+        let mut demoted = 0;
+
+        for (mut node, event) in root.iter() {
+            if event != IterEventType::Enter {
+                continue;
             }
-            match node.append_child(&mut child) {
-                Err(_) => assert!(
-                    !INLINE_HTML_CHILDREN.contains(&other_type),
-                    "{:?} should be able to append, but was not",
-                    other_type
-                ),
-                Ok(_) => assert!(
-                    INLINE_HTML_CHILDREN.contains(&other_type),
-                    "{:?} should not have been able to append, but was",
-                    other_type
-                ),
+
+            if let Node::Heading(ref mut heading) = node {
+                if heading.get_level() != 1 {
+                    continue;
+                }
+
+                if seen_first {
+                    heading.set_level(2)?;
+                    demoted += 1;
+                } else {
+                    seen_first = true;
+                }
             }
         }
+
+        Ok(demoted)
     }
 
-    #[test]
-    fn test_custom_inline_children() {
-        for i in 1..21 {
-            let node_type = NodeType::CMarkNodeCustomInline;
-            let other_type = NodeType::try_from(i).unwrap();
-            let mut node = Node::from_type(node_type).unwrap();
-            let mut child = Node::from_type(other_type.clone()).unwrap();
-            match node.can_append_child(&child).unwrap() {
-                true => assert!(
-                    CUSTOM_INLINE_CHILDREN.contains(&other_type),
-                    "{:?} should not have been a valid block quote child, but was",
-                    other_type
-                ),
-                false => assert!(
-                    !CUSTOM_INLINE_CHILDREN.contains(&other_type),
-                    "{:?} should be a valid block quote child, but was not",
-                    other_type
-                ),
-            }
-            match node.append_child(&mut child) {
-                Err(_) => assert!(
-                    !CUSTOM_INLINE_CHILDREN.contains(&other_type),
-                    "{:?} should be able to append, but was not",
-                    other_type
-                ),
-                Ok(_) => assert!(
-                    CUSTOM_INLINE_CHILDREN.contains(&other_type),
-                    "{:?} should not have been able to append, but was",
-                    other_type
-                ),
+    /// Inserts a table of contents after the first level 1 `Heading` of the document, or at the
+    /// top of the document if there is none
+    ///
+    /// The table of contents is a nested bullet `List` of `Link`s to every `Heading` of at most
+    /// `max_level`, using the heading slugs as fragment URLs. Nothing is inserted if there are no
+    /// such headings.
+    pub fn insert_toc(&self, max_level: u32) -> DoogieResult<()> {
+        let mut root = Node::from_raw(self.resource.pointer)?;
+        let mut entries = Vec::new();
+
+        for (node, slug) in root.heading_slugs()? {
+            let level = match node {
+                Node::Heading(ref heading) => heading.get_level(),
+                _ => continue,
+            };
+
+            if level <= max_level as usize {
+                entries.push((level, node.to_plain_text()?.trim().to_string(), slug));
             }
         }
+
+        if entries.is_empty() {
+            return Ok(());
+        }
+
+        let mut toc = toc_list(&entries)?;
+        let first_title = root.child_nodes()?.into_iter().find(|child| match *child {
+            Node::Heading(ref heading) => heading.get_level() == 1,
+            _ => false,
+        });
+        let next = match first_title {
+            Some(title) => title.next_sibling()?,
+            None => root.first_child()?,
+        };
+
+        match next {
+            Some(mut next) => next.insert_before(&mut toc),
+            None => root.append_child(&mut toc),
+        }
     }
 
-    #[test]
-    fn test_emph_children() {
-        for i in 1..21 {
-            let node_type = NodeType::CMarkNodeEmph;
-            let other_type = NodeType::try_from(i).unwrap();
-            let mut node = Node::from_type(node_type).unwrap();
-            let mut child = Node::from_type(other_type.clone()).unwrap();
-            match node.can_append_child(&child).unwrap() {
-                true => assert!(
-                    EMPH_CHILDREN.contains(&other_type),
-                    "{:?} should not have been a valid block quote child, but was",
-                    other_type
-                ),
-                false => assert!(
-                    !EMPH_CHILDREN.contains(&other_type),
-                    "{:?} should be a valid block quote child, but was not",
-                    other_type
-                ),
+    /// Inserts an empty `<a id="slug"></a>` `HtmlInline` anchor at the start of every `Heading`
+    /// of the document
+    ///
+    /// The anchors use the heading slugs, so repeated headings get unique ids. Returns the number
+    /// of anchors inserted.
+    pub fn insert_heading_anchors(&self) -> DoogieResult<usize> {
+        let root = Node::from_raw(self.resource.pointer)?;
+        let mut inserted = 0;
+
+        for (mut heading, slug) in root.heading_slugs()? {
+            let mut anchor = Node::from_type(NodeType::CMarkNodeHtmlInline)?;
+            if let Node::HtmlInline(ref mut html) = anchor {
+                html.set_content(&format!("<a id=\"{}\"></a>", slug))?;
             }
-            match node.append_child(&mut child) {
-                Err(_) => assert!(
-                    !EMPH_CHILDREN.contains(&other_type),
-                    "{:?} should be able to append, but was not",
-                    other_type
-                ),
-                Ok(_) => assert!(
-                    EMPH_CHILDREN.contains(&other_type),
-                    "{:?} should not have been able to append, but was",
-                    other_type
-                ),
+
+            match heading.first_child()? {
+                Some(mut first) => first.insert_before(&mut anchor)?,
+                None => heading.append_child(&mut anchor)?,
             }
+            inserted += 1;
         }
+
+        Ok(inserted)
     }
 
-    #[test]
-    fn test_strong_children() {
-        for i in 1..21 {
-            let node_type = NodeType::CMarkNodeStrong;
-            let other_type = NodeType::try_from(i).unwrap();
-            let mut node = Node::from_type(node_type).unwrap();
-            let mut child = Node::from_type(other_type.clone()).unwrap();
-            match node.can_append_child(&child).unwrap() {
-                true => assert!(
-                    STRONG_CHILDREN.contains(&other_type),
-                    "{:?} should not have been a valid block quote child, but was",
-                    other_type
-                ),
-                false => assert!(
-                    !STRONG_CHILDREN.contains(&other_type),
-                    "{:?} should be a valid block quote child, but was not",
-                    other_type
-                ),
+    /// Renders each top-level block of the document into its own html fragment
+    ///
+    /// The fragments are paired with the block they were rendered from, in document order.
+    pub fn block_html_fragments(&self) -> DoogieResult<Vec<(Node, String)>> {
+        let root = Node::from_raw(self.resource.pointer)?;
+        let mut fragments = Vec::new();
+
+        for block in root.child_nodes()? {
+            let fragment = block.render_html();
+            fragments.push((block, fragment));
+        }
+
+        Ok(fragments)
+    }
+
+    /// Replaces every `Heading` of the document deeper than `max_level` with a `Paragraph` holding
+    /// the heading text in a `Strong`
+    ///
+    /// Inline formatting within the converted headings is dropped. Returns the number of headings
+    /// converted.
+    pub fn cap_heading_depth(&self, max_level: u32) -> DoogieResult<usize> {
+        let root = Node::from_raw(self.resource.pointer)?;
+        let mut deep_headings = Vec::new();
+
+        for (node, event) in root.iter() {
+            if event != IterEventType::Enter {
+                continue;
             }
-            match node.append_child(&mut child) {
-                Err(_) => assert!(
-                    !STRONG_CHILDREN.contains(&other_type),
-                    "{:?} should be able to append, but was not",
-                    other_type
-                ),
-                Ok(_) => assert!(
-                    STRONG_CHILDREN.contains(&other_type),
-                    "{:?} should not have been able to append, but was",
-                    other_type
-                ),
+
+            let level = match node {
+                Node::Heading(ref heading) => heading.get_level(),
+                _ => continue,
+            };
+            if level > max_level as usize {
+                deep_headings.push(node);
             }
         }
+
+        let converted = deep_headings.len();
+        for mut heading in deep_headings {
+            let mut paragraph = strong_paragraph(heading.to_plain_text()?.trim())?;
+            heading.replace_with(&mut paragraph)?;
+        }
+
+        Ok(converted)
     }
+}
 
-    #[test]
-    fn test_link_children() {
-        for i in 1..21 {
-            let node_type = NodeType::CMarkNodeLink;
-            let other_type = NodeType::try_from(i).unwrap();
-            let mut node = Node::from_type(node_type).unwrap();
-            let mut child = Node::from_type(other_type.clone()).unwrap();
-            match node.can_append_child(&child).unwrap() {
-                true => assert!(
-                    LINK_CHILDREN.contains(&other_type),
-                    "{:?} should not have been a valid block quote child, but was",
+/// Represents a Block Quote element in CommonMark
+pub struct BlockQuote {
+    resource: Resource,
+}
+
+impl BlockQuote {
+    /// Constructs a new `BlockQuote`
+    pub fn new() -> Self {
+        Self {
+            resource: Resource::from_node_type(
+                NodeType::CMarkNodeBlockQuote,
+                Rc::new(ResourceManager::new()),
+            ),
+        }
+    }
+
+    /// Returns the attribution of the current BlockQuote, if any
+    ///
+    /// The attribution is the last line of the last `Paragraph` of the quote when that line starts
+    /// with an em dash. It is returned without the dash and surrounding whitespace.
+    pub fn attribution(&self) -> DoogieResult<Option<String>> {
+        let quote = Node::from_raw(self.resource.pointer)?;
+        let paragraph = match quote.last_child()? {
+            Some(paragraph @ Node::Paragraph(_)) => paragraph,
+            _ => return Ok(None),
+        };
+
+        let text = paragraph.to_plain_text()?;
+        let last_line = text.trim_end().lines().last().unwrap_or("").trim();
+        if last_line.starts_with('\u{2014}') {
+            Ok(Some(last_line['\u{2014}'.len_utf8()..].trim().to_string()))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// Represents a List element in CommonMark
+///
+/// Lists are meta-containers in that they are classified as container blocks in CommonMark, but can
+/// only contain `Item` elements as children.
+pub struct List {
+    resource: Resource,
+}
+
+impl List {
+    /// Constructs a new `List`
+    pub fn new() -> Self {
+        Self {
+            resource: Resource::from_node_type(
+                NodeType::CMarkNodeList,
+                Rc::new(ResourceManager::new()),
+            ),
+        }
+    }
+
+    /// Returns an enum representing the type of list i.e. Bullet or Ordered
+    pub fn get_list_type(&self) -> DoogieResult<ListType> {
+        unsafe { ListType::try_from(cmark_node_get_list_type(self.resource.pointer) as u32) }
+    }
+
+    /// Returns the delimiter type used in the case of ordered lists.
+    pub fn get_delim_type(&self) -> DoogieResult<DelimType> {
+        unsafe { DelimType::try_from(cmark_node_get_list_delim(self.resource.pointer) as u32) }
+    }
+
+    /// Sets the type of the current List
+    pub fn set_list_type(&mut self, list_type: ListType) -> DoogieResult<u32> {
+        let result: i32;
+        unsafe {
+            result = cmark_node_set_list_type(self.resource.pointer, u32::from(list_type) as c_int);
+        }
+
+        match result {
+            1 => Ok(1),
+            err => Err(DoogieError::ReturnCode(err as u32)),
+        }
+    }
+
+    /// Sets the delimiter type used by the current List when it is ordered
+    pub fn set_delim_type(&mut self, delim: DelimType) -> DoogieResult<u32> {
+        let result: i32;
+        unsafe {
+            result = cmark_node_set_list_delim(self.resource.pointer, u32::from(delim) as c_int);
+        }
+
+        match result {
+            1 => Ok(1),
+            err => Err(DoogieError::ReturnCode(err as u32)),
+        }
+    }
+
+    /// Returns the starting number of the current List when it is ordered
+    pub fn get_start(&self) -> u32 {
+        unsafe { cmark_node_get_list_start(self.resource.pointer) as u32 }
+    }
+
+    /// Sets the starting number of the current List when it is ordered
+    pub fn set_start(&mut self, start: u32) -> DoogieResult<u32> {
+        let result: i32;
+        unsafe {
+            result = cmark_node_set_list_start(self.resource.pointer, start as c_int);
+        }
+
+        match result {
+            1 => Ok(1),
+            err => Err(DoogieError::ReturnCode(err as u32)),
+        }
+    }
+
+    /// Determines if the current List is tight, i.e. its items are not separated by blank lines
+    pub fn is_tight(&self) -> bool {
+        unsafe { cmark_node_get_list_tight(self.resource.pointer) == 1 }
+    }
+
+    /// Sets whether the current List is tight
+    pub fn set_tight(&mut self, tight: bool) -> DoogieResult<u32> {
+        let result: i32;
+        unsafe {
+            result = cmark_node_set_list_tight(self.resource.pointer, tight as c_int);
+        }
+
+        match result {
+            1 => Ok(1),
+            err => Err(DoogieError::ReturnCode(err as u32)),
+        }
+    }
+
+    /// Constructs an ordered List with an `Item` holding a `Paragraph` of text for each of the
+    /// given strings
+    pub fn ordered_from(items: &[&str], start: u32, delim: DelimType) -> DoogieResult<Node> {
+        let mut list = Node::from_type(NodeType::CMarkNodeList)?;
+
+        if let Node::List(ref mut ordered) = list {
+            ordered.set_list_type(ListType::CMarkOrderedList)?;
+            ordered.set_delim_type(delim)?;
+            ordered.set_start(start)?;
+        }
+
+        for text in items {
+            let mut item = Node::from_type(NodeType::CMarkNodeItem)?;
+            let mut paragraph = Node::from_type(NodeType::CMarkNodeParagraph)?;
+            let mut content = Node::from_type(NodeType::CMarkNodeText)?;
+
+            if let Node::Text(ref mut content) = content {
+                content.set_content(&text.to_string())?;
+            }
+
+            paragraph.append_child(&mut content)?;
+            item.append_child(&mut paragraph)?;
+            list.append_child(&mut item)?;
+        }
+
+        Ok(list)
+    }
+
+    /// Returns whether each `Item` of the current List holds block content beyond a single
+    /// `Paragraph`, such as several paragraphs or a nested `List`
+    pub fn item_complexity(&self) -> DoogieResult<Vec<bool>> {
+        let list = Node::from_raw(self.resource.pointer)?;
+        let mut complexity = Vec::new();
+
+        for item in list.child_nodes()? {
+            let children = item.child_nodes()?;
+            let simple = match children.len() {
+                0 => true,
+                1 => match children[0] {
+                    Node::Paragraph(_) => true,
+                    _ => false,
+                },
+                _ => false,
+            };
+            complexity.push(!simple);
+        }
+
+        Ok(complexity)
+    }
+}
+
+/// Represents a List Item in CommonMark
+pub struct Item {
+    resource: Resource,
+}
+
+impl Item {
+    /// Constructs a new `Item`
+    pub fn new() -> Self {
+        Self {
+            resource: Resource::from_node_type(
+                NodeType::CMarkNodeItem,
+                Rc::new(ResourceManager::new()),
+            ),
+        }
+    }
+}
+
+/// Represents a Code Block in CommonMark
+pub struct CodeBlock {
+    resource: Resource,
+}
+
+impl CodeBlock {
+    /// Constructs a new `CodeBlock`
+    pub fn new() -> Self {
+        Self {
+            resource: Resource::from_node_type(
+                NodeType::CMarkNodeCodeBlock,
+                Rc::new(ResourceManager::new()),
+            ),
+        }
+    }
+
+    /// Returns the info text in the case of a Fenced Code Block
+    pub fn get_fence_info(&self) -> DoogieResult<String> {
+        let result;
+        unsafe {
+            result = cmark_node_get_fence_info(self.resource.pointer);
+        }
+
+        literal_to_string(result)
+    }
+
+    /// Sets the info text for the code block
+    pub fn set_fence_info(&mut self, info: &String) -> DoogieResult<u32> {
+        let info = CString::new(info.as_bytes())?;
+        let result: i32;
+        unsafe {
+            result = cmark_node_set_fence_info(self.resource.pointer, info.as_ptr());
+        }
+
+        match result {
+            1 => Ok(1),
+            err => Err(DoogieError::ReturnCode(err as u32)),
+        }
+    }
+
+    /// Sets the info text for the code block, rejecting text that would break the rendered fence
+    ///
+    /// Returns `DoogieError::InvalidFenceInfo` with the offending character if the info text
+    /// contains a backtick, which would end a backtick fence, or a line break.
+    pub fn set_fence_info_checked(&mut self, info: &str) -> DoogieResult<u32> {
+        if let Some(c) = info.chars().find(|&c| c == '`' || c == '\n' || c == '\r') {
+            return Err(DoogieError::InvalidFenceInfo(c));
+        }
+
+        self.set_fence_info(&info.to_string())
+    }
+
+    /// Parses the info text of the Code Block into structured attributes
+    ///
+    /// A Pandoc style attribute block such as `{.rust #example .numberLines startFrom=10}` yields
+    /// its id, classes and key-value pairs, with the first class taken as the language. Otherwise
+    /// the first word of the info text is taken as the language.
+    pub fn fence_attributes(&self) -> DoogieResult<FenceAttributes> {
+        let info = self.get_fence_info()?;
+        let info = info.trim();
+        let mut attributes = FenceAttributes::default();
+
+        if info.starts_with('{') && info.ends_with('}') {
+            for token in info[1..info.len() - 1].split_whitespace() {
+                if let Some(class) = token.strip_prefix('.') {
+                    attributes.classes.push(class.to_string());
+                } else if let Some(id) = token.strip_prefix('#') {
+                    attributes.id = Some(id.to_string());
+                } else if let Some(index) = token.find('=') {
+                    let value = token[index + 1..].trim_matches('"');
+                    attributes
+                        .kvs
+                        .insert(token[..index].to_string(), value.to_string());
+                }
+            }
+            attributes.language = attributes.classes.first().cloned();
+        } else {
+            attributes.language = info.split_whitespace().next().map(|word| word.to_string());
+        }
+
+        Ok(attributes)
+    }
+
+    /// Returns the textual content of the current Code Block with each line prefixed by its
+    /// number, counting from `start`
+    ///
+    /// Numbers are right-aligned to the width of the largest one and separated from the line by a
+    /// space.
+    pub fn content_with_line_numbers(&self, start: usize) -> DoogieResult<String> {
+        let content = self.get_content()?;
+        let lines: Vec<&str> = content.lines().collect();
+        let width = (start + lines.len().saturating_sub(1)).to_string().len();
+        let mut numbered = String::new();
+
+        for (index, line) in lines.iter().enumerate() {
+            let number = format!("{:>width$}", start + index, width = width);
+            if line.is_empty() {
+                numbered.push_str(&number);
+            } else {
+                numbered.push_str(&format!("{} {}", number, line));
+            }
+            numbered.push('\n');
+        }
+
+        Ok(numbered)
+    }
+
+    /// Determines if the current Code Block was written with a code fence rather than indented
+    ///
+    /// libcmark does not record this, so it is read from the given source, which must be the text
+    /// the document was parsed from. A fenced block starts at its opening fence of backticks or
+    /// tildes, while an indented block starts at its first line of content, even if that line looks
+    /// like a fence. A block without a source position, such as a constructed one, is considered
+    /// fenced only when it has info text.
+    pub fn is_fenced(&self, source: &str) -> DoogieResult<bool> {
+        let (start_line, start_column);
+        unsafe {
+            start_line = cmark_node_get_start_line(self.resource.pointer) as usize;
+            start_column = cmark_node_get_start_column(self.resource.pointer) as usize;
+        }
+
+        if start_line == 0 || start_column == 0 {
+            return Ok(!self.get_fence_info()?.trim().is_empty());
+        }
+
+        let start = source
+            .split('\n')
+            .nth(start_line - 1)
+            .and_then(|line| line.get(start_column - 1..))
+            .unwrap_or("")
+            .trim_end_matches('\r');
+        if !(start.starts_with("```") || start.starts_with("~~~")) {
+            return Ok(false);
+        }
+
+        Ok(self.get_content()?.lines().next() != Some(start))
+    }
+
+    /// Returns the textual content of the current Code Block element
+    pub fn get_content(&self) -> DoogieResult<String> {
+        let result;
+        unsafe {
+            result = cmark_node_get_literal(self.resource.pointer);
+        }
+
+        literal_to_string(result)
+    }
+
+    /// Sets the textual content of the current Code Block element
+    pub fn set_content(&mut self, content: &String) -> DoogieResult<u32> {
+        let content = CString::new(content.as_bytes())?;
+        let result: i32;
+        unsafe {
+            result = cmark_node_set_literal(self.resource.pointer, content.as_ptr());
+        }
+
+        match result {
+            1 => Ok(1 as u32),
+            i => Err(DoogieError::ReturnCode(i as u32)),
+        }
+    }
+}
+
+/// A run of text along with the inline formatting that applies to it
+#[derive(Debug, PartialEq)]
+pub struct FormattedRun {
+    /// Textual content of the run
+    pub text: String,
+    /// Whether the run is within an `Emph` element
+    pub emphasis: bool,
+    /// Whether the run is within a `Strong` element
+    pub strong: bool,
+    /// Whether the run is inline `Code`
+    pub code: bool,
+    /// URL of the nearest `Link` enclosing the run, if any
+    pub link_url: Option<String>,
+}
+
+/// Attributes parsed from the info text of a fenced `CodeBlock`
+#[derive(Debug, Default, PartialEq)]
+pub struct FenceAttributes {
+    /// Language of the code block
+    pub language: Option<String>,
+    /// Identifier given by a `#id` attribute
+    pub id: Option<String>,
+    /// Classes given by `.class` attributes
+    pub classes: Vec<String>,
+    /// Values given by `key=value` attributes
+    pub kvs: HashMap<String, String>,
+}
+
+/// A position in a text document as defined by the Language Server Protocol
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LspPosition {
+    /// Line of the position, starting at 0
+    pub line: u32,
+    /// Offset of the position in the line, counted in UTF-16 code units
+    pub character: u32,
+}
+
+/// A range in a text document as defined by the Language Server Protocol
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LspRange {
+    /// Position of the start of the range
+    pub start: LspPosition,
+    /// Position just past the end of the range
+    pub end: LspPosition,
+}
+
+/// Represents a block of HTML in CommonMark
+pub struct HtmlBlock {
+    resource: Resource,
+}
+
+impl HtmlBlock {
+    /// Constructs a new `HtmlBlock`
+    pub fn new() -> Self {
+        Self {
+            resource: Resource::from_node_type(
+                NodeType::CMarkNodeHtmlBlock,
+                Rc::new(ResourceManager::new()),
+            ),
+        }
+    }
+
+    /// Returns the raw HTML content of the current HtmlBlock element
+    pub fn get_content(&self) -> DoogieResult<String> {
+        let result;
+        unsafe {
+            result = cmark_node_get_literal(self.resource.pointer);
+        }
+
+        literal_to_string(result)
+    }
+
+    /// Sets the raw HTML content of the current HtmlBlock element
+    pub fn set_content(&mut self, content: &String) -> DoogieResult<u32> {
+        let content = CString::new(content.as_bytes())?;
+        let result: i32;
+        unsafe {
+            result = cmark_node_set_literal(self.resource.pointer, content.as_ptr());
+        }
+
+        match result {
+            1 => Ok(1 as u32),
+            i => Err(DoogieError::ReturnCode(i as u32)),
+        }
+    }
+}
+
+/// Represents an ambiguous Block Element
+pub struct CustomBlock {
+    resource: Resource,
+}
+
+impl CustomBlock {
+    /// Constructs a new `CustomBlock`
+    pub fn new() -> Self {
+        Self {
+            resource: Resource::from_node_type(
+                NodeType::CMarkNodeCustomBlock,
+                Rc::new(ResourceManager::new()),
+            ),
+        }
+    }
+
+    /// Returns the literal text output before the contents of the current Custom Block element
+    pub fn get_on_enter(&self) -> DoogieResult<String> {
+        let result;
+        unsafe {
+            result = cmark_node_get_on_enter(self.resource.pointer);
+        }
+
+        literal_to_string(result)
+    }
+
+    /// Sets the literal text output before the contents of the current Custom Block element
+    pub fn set_on_enter(&mut self, on_enter: &String) -> DoogieResult<u32> {
+        let on_enter = CString::new(on_enter.as_bytes())?;
+        let result: i32;
+        unsafe {
+            result = cmark_node_set_on_enter(self.resource.pointer, on_enter.as_ptr());
+        }
+
+        match result {
+            1 => Ok(1 as u32),
+            i => Err(DoogieError::ReturnCode(i as u32)),
+        }
+    }
+
+    /// Returns the literal text output after the contents of the current Custom Block element
+    pub fn get_on_exit(&self) -> DoogieResult<String> {
+        let result;
+        unsafe {
+            result = cmark_node_get_on_exit(self.resource.pointer);
+        }
+
+        literal_to_string(result)
+    }
+
+    /// Sets the literal text output after the contents of the current Custom Block element
+    pub fn set_on_exit(&mut self, on_exit: &String) -> DoogieResult<u32> {
+        let on_exit = CString::new(on_exit.as_bytes())?;
+        let result: i32;
+        unsafe {
+            result = cmark_node_set_on_exit(self.resource.pointer, on_exit.as_ptr());
+        }
+
+        match result {
+            1 => Ok(1 as u32),
+            i => Err(DoogieError::ReturnCode(i as u32)),
+        }
+    }
+}
+
+/// Represents a Paragraph element in CommonMark
+pub struct Paragraph {
+    resource: Resource,
+}
+
+impl Paragraph {
+    /// Constructs a new `Paragraph`
+    pub fn new() -> Self {
+        Self {
+            resource: Resource::from_node_type(
+                NodeType::CMarkNodeParagraph,
+                Rc::new(ResourceManager::new()),
+            ),
+        }
+    }
+}
+
+/// Represents a Heading element in CommonMark
+pub struct Heading {
+    resource: Resource,
+}
+
+impl Heading {
+    /// Constructs a new `Heading`
+    pub fn new() -> Self {
+        Self {
+            resource: Resource::from_node_type(
+                NodeType::CMarkNodeHeading,
+                Rc::new(ResourceManager::new()),
+            ),
+        }
+    }
+
+    /// Returns the heading level of the current Heading
+    pub fn get_level(&self) -> usize {
+        unsafe { cmark_node_get_heading_level(self.resource.pointer) as usize }
+    }
+
+    /// Sets the heading level of the current Heading
+    ///
+    /// The level must be between 1 and 6.
+    pub fn set_level(&mut self, level: u32) -> DoogieResult<u32> {
+        if !(1..=6).contains(&level) {
+            return Err(DoogieError::InvalidHeadingLevel(level));
+        }
+
+        let result: i32;
+        unsafe {
+            result = cmark_node_set_heading_level(self.resource.pointer, level as c_int);
+        }
+
+        match result {
+            1 => Ok(1 as u32),
+            i => Err(DoogieError::ReturnCode(i as u32)),
+        }
+    }
+}
+
+/// Represents a Thematic Break element in CommonMark
+pub struct ThematicBreak {
+    resource: Resource,
+}
+
+impl ThematicBreak {
+    /// Constructs a new `ThematicBreak`
+    pub fn new() -> Self {
+        Self {
+            resource: Resource::from_node_type(
+                NodeType::CMarkNodeThematicBreak,
+                Rc::new(ResourceManager::new()),
+            ),
+        }
+    }
+}
+
+/// Represents a Text element in CommonMark
+pub struct Text {
+    resource: Resource,
+}
+
+impl Text {
+    /// Constructs a new `Text`
+    pub fn new() -> Self {
+        Text {
+            resource: Resource::from_node_type(
+                NodeType::CMarkNodeText,
+                Rc::new(ResourceManager::new()),
+            ),
+        }
+    }
+
+    /// Returns the textual content of the current Text element
+    pub fn get_content(&self) -> DoogieResult<String> {
+        let result;
+        unsafe {
+            result = cmark_node_get_literal(self.resource.pointer);
+        }
+
+        literal_to_string(result)
+    }
+
+    /// Sets the textual content of the current Text element
+    pub fn set_content(&mut self, content: &String) -> DoogieResult<u32> {
+        let content = CString::new(content.as_bytes())?;
+        let result: i32;
+        unsafe {
+            result = cmark_node_set_literal(self.resource.pointer, content.as_ptr());
+        }
+
+        match result {
+            1 => Ok(1 as u32),
+            i => Err(DoogieError::ReturnCode(i as u32)),
+        }
+    }
+}
+
+/// Represents a Soft Break element in CommonMark
+pub struct SoftBreak {
+    resource: Resource,
+}
+
+impl SoftBreak {
+    /// Constructs a new `SoftBreak`
+    pub fn new() -> Self {
+        Self {
+            resource: Resource::from_node_type(
+                NodeType::CMarkNodeSoftbreak,
+                Rc::new(ResourceManager::new()),
+            ),
+        }
+    }
+}
+
+/// Represents a Line Break element in CommonMark
+pub struct LineBreak {
+    resource: Resource,
+}
+
+impl LineBreak {
+    /// Constructs a new `LineBreak`
+    pub fn new() -> Self {
+        Self {
+            resource: Resource::from_node_type(
+                NodeType::CMarkNodeLinebreak,
+                Rc::new(ResourceManager::new()),
+            ),
+        }
+    }
+}
+
+/// Represents an inline Code element in CommonMark
+pub struct Code {
+    resource: Resource,
+}
+
+impl Code {
+    /// Constructs a new `Code`
+    pub fn new() -> Self {
+        Self {
+            resource: Resource::from_node_type(
+                NodeType::CMarkNodeCode,
+                Rc::new(ResourceManager::new()),
+            ),
+        }
+    }
+
+    /// Returns the textual content of the current Text element
+    pub fn get_content(&self) -> DoogieResult<String> {
+        let result;
+        unsafe {
+            result = cmark_node_get_literal(self.resource.pointer);
+        }
+
+        literal_to_string(result)
+    }
+
+    /// Sets the textual content of the current Text element
+    pub fn set_content(&mut self, content: &String) -> DoogieResult<u32> {
+        let content = CString::new(content.as_bytes())?;
+        let result: i32;
+        unsafe {
+            result = cmark_node_set_literal(self.resource.pointer, content.as_ptr());
+        }
+
+        match result {
+            1 => Ok(1 as u32),
+            i => Err(DoogieError::ReturnCode(i as u32)),
+        }
+    }
+}
+
+/// Represents an inline HTML element in CommonMark
+pub struct HtmlInline {
+    resource: Resource,
+}
+
+impl HtmlInline {
+    /// Constructs a new `HtmlInline`
+    pub fn new() -> Self {
+        Self {
+            resource: Resource::from_node_type(
+                NodeType::CMarkNodeHtmlInline,
+                Rc::new(ResourceManager::new()),
+            ),
+        }
+    }
+
+    /// Returns the raw HTML content of the current HtmlInline element
+    pub fn get_content(&self) -> DoogieResult<String> {
+        let result;
+        unsafe {
+            result = cmark_node_get_literal(self.resource.pointer);
+        }
+
+        literal_to_string(result)
+    }
+
+    /// Sets the raw HTML content of the current HtmlInline element
+    pub fn set_content(&mut self, content: &String) -> DoogieResult<u32> {
+        let content = CString::new(content.as_bytes())?;
+        let result: i32;
+        unsafe {
+            result = cmark_node_set_literal(self.resource.pointer, content.as_ptr());
+        }
+
+        match result {
+            1 => Ok(1 as u32),
+            i => Err(DoogieError::ReturnCode(i as u32)),
+        }
+    }
+}
+
+/// Represents an ambiguous inline element
+pub struct CustomInline {
+    resource: Resource,
+}
+
+impl CustomInline {
+    /// Constructs a new `CustomInline`
+    pub fn new() -> Self {
+        Self {
+            resource: Resource::from_node_type(
+                NodeType::CMarkNodeCustomInline,
+                Rc::new(ResourceManager::new()),
+            ),
+        }
+    }
+
+    /// Returns the literal text output before the contents of the current Custom Inline element
+    pub fn get_on_enter(&self) -> DoogieResult<String> {
+        let result;
+        unsafe {
+            result = cmark_node_get_on_enter(self.resource.pointer);
+        }
+
+        literal_to_string(result)
+    }
+
+    /// Sets the literal text output before the contents of the current Custom Inline element
+    pub fn set_on_enter(&mut self, on_enter: &String) -> DoogieResult<u32> {
+        let on_enter = CString::new(on_enter.as_bytes())?;
+        let result: i32;
+        unsafe {
+            result = cmark_node_set_on_enter(self.resource.pointer, on_enter.as_ptr());
+        }
+
+        match result {
+            1 => Ok(1 as u32),
+            i => Err(DoogieError::ReturnCode(i as u32)),
+        }
+    }
+
+    /// Returns the literal text output after the contents of the current Custom Inline element
+    pub fn get_on_exit(&self) -> DoogieResult<String> {
+        let result;
+        unsafe {
+            result = cmark_node_get_on_exit(self.resource.pointer);
+        }
+
+        literal_to_string(result)
+    }
+
+    /// Sets the literal text output after the contents of the current Custom Inline element
+    pub fn set_on_exit(&mut self, on_exit: &String) -> DoogieResult<u32> {
+        let on_exit = CString::new(on_exit.as_bytes())?;
+        let result: i32;
+        unsafe {
+            result = cmark_node_set_on_exit(self.resource.pointer, on_exit.as_ptr());
+        }
+
+        match result {
+            1 => Ok(1 as u32),
+            i => Err(DoogieError::ReturnCode(i as u32)),
+        }
+    }
+}
+
+/// Represenets an Emph element in CommonMark
+pub struct Emph {
+    resource: Resource,
+}
+
+impl Emph {
+    /// Constructs a new `Emph`
+    pub fn new() -> Self {
+        Self {
+            resource: Resource::from_node_type(
+                NodeType::CMarkNodeEmph,
+                Rc::new(ResourceManager::new()),
+            ),
+        }
+    }
+}
+
+/// Represents a Strong element in CommonMark
+pub struct Strong {
+    resource: Resource,
+}
+
+impl Strong {
+    /// Constructs a new `Strong`
+    pub fn new() -> Self {
+        Self {
+            resource: Resource::from_node_type(
+                NodeType::CMarkNodeStrong,
+                Rc::new(ResourceManager::new()),
+            ),
+        }
+    }
+}
+
+/// Represents a Link element in CommonMark
+pub struct Link {
+    resource: Resource,
+}
+
+impl Link {
+    /// Constructs a new `Link`
+    pub fn new() -> Self {
+        Self {
+            resource: Resource::from_node_type(
+                NodeType::CMarkNodeLink,
+                Rc::new(ResourceManager::new()),
+            ),
+        }
+    }
+
+    /// Returns the URL portion of the Link
+    pub fn get_url(&self) -> DoogieResult<String> {
+        let result;
+        unsafe {
+            result = cmark_node_get_url(self.resource.pointer);
+        }
+
+        literal_to_string(result)
+    }
+
+    /// Sets the URL portion of the Link
+    pub fn set_url(&mut self, url: &String) -> DoogieResult<u32> {
+        let url = CString::new(url.as_bytes())?;
+        let result: i32;
+        unsafe {
+            result = cmark_node_set_url(self.resource.pointer, url.as_ptr());
+        }
+
+        match result {
+            1 => Ok(1),
+            err => Err(DoogieError::ReturnCode(err as u32)),
+        }
+    }
+
+    /// Returns the title portion of the Link
+    pub fn get_title(&self) -> DoogieResult<String> {
+        let result;
+        unsafe {
+            result = cmark_node_get_title(self.resource.pointer);
+        }
+
+        literal_to_string(result)
+    }
+
+    /// Sets the title of the Link
+    pub fn set_title(&mut self, title: &String) -> DoogieResult<u32> {
+        let title = CString::new(title.as_bytes())?;
+        let result: i32;
+        unsafe {
+            result = cmark_node_set_title(self.resource.pointer, title.as_ptr());
+        }
+
+        match result {
+            1 => Ok(1),
+            err => Err(DoogieError::ReturnCode(err as u32)),
+        }
+    }
+}
+
+/// Represents an Image element in CommonMark
+pub struct Image {
+    resource: Resource,
+}
+
+impl Image {
+    /// Constructs a new `Image`
+    pub fn new() -> Self {
+        Self {
+            resource: Resource::from_node_type(
+                NodeType::CMarkNodeImage,
+                Rc::new(ResourceManager::new()),
+            ),
+        }
+    }
+
+    /// Returns the URL portion of the Image
+    pub fn get_url(&self) -> DoogieResult<String> {
+        let result;
+        unsafe {
+            result = cmark_node_get_url(self.resource.pointer);
+        }
+
+        literal_to_string(result)
+    }
+
+    /// Sets the URL portion of the Image
+    pub fn set_url(&mut self, url: &String) -> DoogieResult<u32> {
+        let url = CString::new(url.as_bytes())?;
+        let result: i32;
+        unsafe {
+            result = cmark_node_set_url(self.resource.pointer, url.as_ptr());
+        }
+
+        match result {
+            1 => Ok(1),
+            err => Err(DoogieError::ReturnCode(err as u32)),
+        }
+    }
+
+    /// Returns the title portion of the Image
+    pub fn get_title(&self) -> DoogieResult<String> {
+        let result;
+        unsafe {
+            result = cmark_node_get_title(self.resource.pointer);
+        }
+
+        literal_to_string(result)
+    }
+
+    /// Sets the title of the Image
+    pub fn set_title(&mut self, title: &String) -> DoogieResult<u32> {
+        let title = CString::new(title.as_bytes())?;
+        let result: i32;
+        unsafe {
+            result = cmark_node_set_title(self.resource.pointer, title.as_ptr());
+        }
+
+        match result {
+            1 => Ok(1),
+            err => Err(DoogieError::ReturnCode(err as u32)),
+        }
+    }
+}
+
+/// Iterator over the subtree rooted in the current node.
+///
+/// NodeIterator is a wrapper around the libcmark iterator and so traverses the subtree using the
+/// same scheme documented [here](https://github.com/commonmark/cmark/blob/a5c83d7a426bda38aac838f9815664f6189d3404/src/cmark.h#L151).
+///
+/// # Examples
+///
+/// Transform all Text Nodes to uppercase
+/// ```
+/// use doogie::{parse_document, Node};
+///
+/// let document = "# My Great Document \
+///     \
+///     * Item 1 \
+///     * Item 2 \
+///     * Item 3";
+///
+/// let root = parse_document(document);
+///
+/// for (mut node, _) in root.iter() {
+///     if let Node::Text(ref mut node) = node {
+///         let content = node.get_content().unwrap();
+///         node.set_content(&content.to_uppercase()).unwrap();
+///     }
+/// }
+/// ```
+///
+/// Remove all level 6 Heading Nodes
+/// ```
+/// use doogie::{parse_document, Node};
+///
+/// let document = "# My Great Document \
+///     \
+///     * Item 1 \
+///     * Item 2 \
+///     * Item 3";
+///
+/// let root = parse_document(document);
+///
+/// for (mut node, _) in root.iter() {
+///     let prune = match node {
+///         Node::Heading(ref heading) => heading.get_level() == 6,
+///         _ => false
+///     };
+///
+///     if prune {
+///         node.unlink();
+///     }
+/// }
+/// ```
+pub struct NodeIterator {
+    /// Raw CMark iterator pointer.
+    pointer: *mut CMarkIterPtr,
+    /// Manager of the root node, held when the iterator was created by consuming the root.
+    manager: Option<Rc<ResourceManager>>,
+}
+
+impl NodeIterator {
+    /// Construct a new instance.
+    fn new(node_ptr: *mut CMarkNodePtr) -> NodeIterator {
+        let pointer;
+        unsafe {
+            pointer = cmark_iter_new(node_ptr);
+        }
+
+        NodeIterator {
+            pointer,
+            manager: None,
+        }
+    }
+}
+
+/// Iterates over the subtree rooted at the `Node`, keeping the tree alive for as long as the
+/// iterator exists
+///
+/// This allows returning an iterator over a document that the caller does not otherwise hold.
+///
+/// # Examples
+///
+/// ```
+/// use doogie::{parse_document, Node, NodeIterator};
+///
+/// fn parse_and_iterate(source: &str) -> NodeIterator {
+///     parse_document(source).into_iter()
+/// }
+///
+/// let headings = parse_and_iterate("# One\n\n## Two\n")
+///     .filter(|&(ref node, _)| match *node {
+///         Node::Heading(_) => true,
+///         _ => false,
+///     })
+///     .count();
+///
+/// // Each heading is both entered and exited
+/// assert_eq!(headings, 4);
+/// ```
+impl IntoIterator for Node {
+    type Item = (Node, IterEventType);
+    type IntoIter = NodeIterator;
+
+    fn into_iter(self) -> NodeIterator {
+        let mut iter = NodeIterator::new(self.pointer());
+        iter.manager = Some(self.manager());
+        iter
+    }
+}
+
+impl Iterator for NodeIterator {
+    type Item = (Node, IterEventType);
+
+    /// Advance the iterator.
+    fn next(&mut self) -> Option<Self::Item> {
+        let event_type;
+        unsafe {
+            event_type = IterEventType::try_from(cmark_iter_next(self.pointer) as u32);
+        }
+
+        match event_type {
+            Ok(IterEventType::Done) | Ok(IterEventType::None) => None,
+            Ok(event) => {
+                let node_pointer;
+                unsafe {
+                    node_pointer = cmark_iter_get_node(self.pointer);
+                }
+                match Node::from_raw(node_pointer) {
+                    Ok(node) => Some((node, event)),
+                    Err(_) => {
+                        error!("Could not instantiate Node from Iterator.");
+                        None
+                    }
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Drop for NodeIterator {
+    /// Free the CMark memory allocated for the iterator.
+    fn drop(&mut self) {
+        unsafe {
+            cmark_iter_free(self.pointer);
+        }
+    }
+}
+
+/// Iterator over the direct children of a node.
+///
+/// The next sibling of each child is looked up before the child is yielded, so the yielded child
+/// may be unlinked or moved without disturbing the iteration.
+///
+/// # Examples
+///
+/// ```
+/// use doogie::parse_document;
+///
+/// let root = parse_document("# Title\n\nText\n");
+///
+/// for child in root.children() {
+///     println!("{:?}", child);
+/// }
+/// ```
+pub struct ChildIterator {
+    /// Raw CMark pointer of the next child to yield.
+    next: *mut CMarkNodePtr,
+}
+
+impl Iterator for ChildIterator {
+    type Item = Node;
+
+    /// Advance the iterator.
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next.is_null() {
+            return None;
+        }
+
+        let current = self.next;
+        unsafe {
+            self.next = cmark_node_next(current);
+        }
+
+        match Node::from_raw(current) {
+            Ok(node) => Some(node),
+            Err(_) => {
+                error!("Could not instantiate Node from ChildIterator.");
+                self.next = ptr::null_mut();
+                None
+            }
+        }
+    }
+}
+
+/// Iterator over the siblings on one side of a node, from the nearest outwards.
+///
+/// Iteration stops at the first missing sibling, or after yielding an error for a sibling that
+/// could not be instantiated.
+///
+/// # Examples
+///
+/// ```
+/// use doogie::{parse_document, Node};
+///
+/// let root = parse_document("# Title\n\nFirst\n\nSecond\n");
+/// let heading = root.first_child().unwrap().unwrap();
+///
+/// for sibling in heading.following_siblings() {
+///     if let Node::Heading(_) = sibling.unwrap() {
+///         break;
+///     }
+/// }
+/// ```
+pub struct SiblingIterator {
+    /// Raw CMark pointer of the last node visited.
+    current: *mut CMarkNodePtr,
+    /// Whether to walk towards the next rather than the previous siblings.
+    forward: bool,
+}
+
+impl Iterator for SiblingIterator {
+    type Item = DoogieResult<Node>;
+
+    /// Advance the iterator.
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.current.is_null() {
+            return None;
+        }
+
+        unsafe {
+            self.current = if self.forward {
+                cmark_node_next(self.current)
+            } else {
+                cmark_node_previous(self.current)
+            };
+        }
+
+        if self.current.is_null() {
+            return None;
+        }
+
+        let result = Node::from_raw(self.current);
+        if result.is_err() {
+            self.current = ptr::null_mut();
+        }
+        Some(result)
+    }
+}
+
+/// Iterator over the ancestors of a node, innermost first.
+///
+/// Iteration stops after the root of the tree, or after yielding an error for an ancestor that
+/// could not be instantiated.
+///
+/// # Examples
+///
+/// ```
+/// use doogie::parse_document;
+///
+/// let root = parse_document("> *Quoted*\n");
+/// let quote = root.first_child().unwrap().unwrap();
+///
+/// assert_eq!(quote.ancestors().count(), 1);
+/// ```
+pub struct AncestorIterator {
+    /// Raw CMark pointer of the last node visited.
+    current: *mut CMarkNodePtr,
+}
+
+impl Iterator for AncestorIterator {
+    type Item = DoogieResult<Node>;
+
+    /// Advance the iterator.
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.current.is_null() {
+            return None;
+        }
+
+        unsafe {
+            self.current = cmark_node_parent(self.current);
+        }
+
+        if self.current.is_null() {
+            return None;
+        }
+
+        let result = Node::from_raw(self.current);
+        if result.is_err() {
+            self.current = ptr::null_mut();
+        }
+        Some(result)
+    }
+}
+
+/// Escapes a string for use as a quoted GraphViz DOT label
+fn escape_dot_label(label: &str) -> String {
+    let mut escaped = String::with_capacity(label.len());
+    for c in label.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+/// Resolves a possibly relative URL against the given base URL
+///
+/// URLs with a scheme, protocol relative URLs and fragment-only URLs are returned unchanged.
+/// Root-relative URLs are resolved against the scheme and host of the base URL and query-only URLs
+/// replace the query of the base URL. Any other URL is joined onto the base URL as if it named a
+/// directory, without resolving `..` segments.
+fn resolve_url(base_url: &str, url: &str) -> String {
+    if url.is_empty() || url.starts_with('#') || url.starts_with("//") || has_url_scheme(url) {
+        return url.to_string();
+    }
+
+    if url.starts_with('/') {
+        return format!("{}{}", url_origin(base_url), url);
+    }
+
+    let path_end = base_url
+        .find(|c| c == '?' || c == '#')
+        .unwrap_or_else(|| base_url.len());
+    let base_path = &base_url[..path_end];
+    if url.starts_with('?') {
+        return format!("{}{}", base_path, url);
+    }
+
+    format!(
+        "{}/{}",
+        base_path.trim_end_matches('/'),
+        url.trim_start_matches("./")
+    )
+}
+
+/// Returns the scheme and host of the given URL, e.g. `https://cdn.example.com`, or an empty
+/// string if the URL has no host
+fn url_origin(url: &str) -> &str {
+    let host_start = match url.find(':') {
+        _ if url.starts_with("//") => 2,
+        Some(index) if has_url_scheme(url) && url[index..].starts_with("://") => index + 3,
+        _ => return "",
+    };
+
+    let host_end = url[host_start..]
+        .find(|c| c == '/' || c == '?' || c == '#')
+        .map_or(url.len(), |index| host_start + index);
+
+    &url[..host_end]
+}
+
+/// Determines if the given URL begins with a scheme such as `https:` or `mailto:`
+fn has_url_scheme(url: &str) -> bool {
+    match url.find(':') {
+        Some(index) => {
+            let scheme = &url[..index];
+            scheme
+                .chars()
+                .next()
+                .map_or(false, |c| c.is_ascii_alphabetic())
+                && scheme
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.')
+        }
+        None => false,
+    }
+}
+
+/// Determines if the given text is a bare URL, such as `https://example.com`, `www.example.com`
+/// or `mailto:user@example.com`
+fn looks_like_url(text: &str) -> bool {
+    if text.is_empty() || text.chars().any(|c| c.is_whitespace()) {
+        return false;
+    }
+
+    let lowercase = text.to_lowercase();
+    if lowercase.starts_with("www.") && text.len() > 4 {
+        return true;
+    }
+
+    has_url_scheme(text) && match text.find(':') {
+        Some(index) => {
+            let rest = &text[index + 1..];
+            (rest.starts_with("//") && rest.len() > 2)
+                || (lowercase.starts_with("mailto:") && rest.contains('@'))
+        }
+        None => false,
+    }
+}
+
+/// Iterator over the types of the nodes in the subtree rooted in the current node.
+///
+/// NodeTypeIterator traverses the subtree in the same way as `NodeIterator`, but only reads the
+/// type of each node rather than constructing a `Node` for it.
+pub struct NodeTypeIterator {
+    /// Raw CMark iterator pointer.
+    pointer: *mut CMarkIterPtr,
+}
+
+impl NodeTypeIterator {
+    /// Construct a new instance.
+    fn new(node_ptr: *mut CMarkNodePtr) -> NodeTypeIterator {
+        let pointer;
+        unsafe {
+            pointer = cmark_iter_new(node_ptr);
+        }
+
+        NodeTypeIterator { pointer }
+    }
+}
+
+impl Iterator for NodeTypeIterator {
+    type Item = (NodeType, IterEventType);
+
+    /// Advance the iterator.
+    fn next(&mut self) -> Option<Self::Item> {
+        let event_type;
+        unsafe {
+            event_type = IterEventType::try_from(cmark_iter_next(self.pointer) as u32);
+        }
+
+        match event_type {
+            Ok(IterEventType::Done) | Ok(IterEventType::None) => None,
+            Ok(event) => {
+                let node_type;
+                unsafe {
+                    let node_pointer = cmark_iter_get_node(self.pointer);
+                    node_type = NodeType::try_from(cmark_node_get_type(node_pointer) as u32);
+                }
+                match node_type {
+                    Ok(node_type) => Some((node_type, event)),
+                    Err(_) => {
+                        error!("Could not determine Node type from Iterator.");
+                        None
+                    }
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Drop for NodeTypeIterator {
+    /// Free the CMark memory allocated for the iterator.
+    fn drop(&mut self) {
+        unsafe {
+            cmark_iter_free(self.pointer);
+        }
+    }
+}
+
+/// Converts heading text into a slug suitable for use as a URL fragment
+///
+/// The text is lowercased, whitespace becomes `-` and everything other than alphanumeric
+/// characters, `-` and `_` is dropped.
+fn slugify(text: &str) -> String {
+    let mut slug = String::new();
+
+    for c in text.trim().chars() {
+        if c.is_alphanumeric() || c == '-' || c == '_' {
+            slug.extend(c.to_lowercase());
+        } else if c.is_whitespace() {
+            slug.push('-');
+        }
+    }
+
+    slug
+}
+
+/// Builds a nested bullet `List` of `Link`s from `(level, text, slug)` heading entries
+fn toc_list(entries: &[(usize, String, String)]) -> DoogieResult<Node> {
+    let base_level = entries.iter().map(|entry| entry.0).min().unwrap_or(1);
+    let mut stack: Vec<(usize, Node, Option<Node>)> =
+        vec![(base_level, Node::from_type(NodeType::CMarkNodeList)?, None)];
+
+    for &(level, ref text, ref slug) in entries {
+        while stack.len() > 1 && stack[stack.len() - 1].0 > level {
+            stack.pop();
+        }
+
+        let nest = {
+            let top = &stack[stack.len() - 1];
+            level > top.0 && top.2.is_some()
+        };
+        if nest {
+            let mut sublist = Node::from_type(NodeType::CMarkNodeList)?;
+            if let Some(&mut (_, _, Some(ref mut item))) = stack.last_mut() {
+                item.append_child(&mut sublist)?;
+            }
+            stack.push((level, sublist, None));
+        }
+
+        let mut item = toc_item(text, slug)?;
+        if let Some(&mut (_, ref mut list, ref mut last_item)) = stack.last_mut() {
+            list.append_child(&mut item)?;
+            *last_item = Some(item);
+        }
+    }
+
+    let (_, list, _) = stack.remove(0);
+    Ok(list)
+}
+
+/// Builds a table of contents `Item` holding a `Link` to the given slug
+fn toc_item(text: &str, slug: &str) -> DoogieResult<Node> {
+    let mut item = Node::from_type(NodeType::CMarkNodeItem)?;
+    let mut paragraph = Node::from_type(NodeType::CMarkNodeParagraph)?;
+    let mut link = Node::from_type(NodeType::CMarkNodeLink)?;
+    let mut label = Node::from_type(NodeType::CMarkNodeText)?;
+
+    if let Node::Text(ref mut label) = label {
+        label.set_content(&text.to_string())?;
+    }
+    if let Node::Link(ref mut link) = link {
+        link.set_url(&format!("#{}", slug))?;
+    }
+
+    link.append_child(&mut label)?;
+    paragraph.append_child(&mut link)?;
+    item.append_child(&mut paragraph)?;
+
+    Ok(item)
+}
+
+/// Replaces the `-` bullet markers of rendered CommonMark with the given markers, one for each
+/// bullet list item in document order
+///
+/// The rendered text is parsed again and markers are only replaced at the start positions of its
+/// items, leaving code and HTML that merely looks like a list untouched.
+fn restore_bullet_markers(rendered: &str, markers: &[char]) -> DoogieResult<String> {
+    let reparsed = parse_bytes_with_options(rendered.as_bytes(), &ParseOptions::default())?;
+    let mut line_offsets = vec![0];
+    line_offsets.extend(rendered.match_indices('\n').map(|(index, _)| index + 1));
+
+    let mut restored = rendered.to_string();
+    let mut markers = markers.iter();
+    for (node, event) in reparsed.iter() {
+        if event != IterEventType::Enter {
+            continue;
+        }
+
+        if let Node::Item(_) = node {
+            if let Some(Node::List(ref list)) = node.parent()? {
+                if list.get_list_type()? != ListType::CMarkBulletList {
+                    continue;
+                }
+
+                let marker = match markers.next() {
+                    Some(marker) => marker,
+                    None => break,
+                };
+                let line = node.get_start_line() as usize;
+                let column = node.get_start_column() as usize;
+                if line == 0 || column == 0 {
+                    continue;
+                }
+
+                if let Some(offset) = line_offsets.get(line - 1).map(|start| start + column - 1) {
+                    if rendered.as_bytes().get(offset) == Some(&b'-') {
+                        restored.replace_range(offset..offset + 1, &marker.to_string());
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(restored)
+}
+
+/// Counts the sentences in the given text, treating an unterminated trailing sentence as one
+fn count_sentences(text: &str) -> usize {
+    let mut count = 0;
+    let mut pending = false;
+
+    for word in text.split_whitespace() {
+        if word.chars().any(|c| c.is_alphanumeric()) {
+            pending = true;
+        }
+
+        let stripped = word.trim_end_matches(|c| c == '"' || c == '\'' || c == ')' || c == ']');
+        let ends_sentence = if stripped.ends_with('!') || stripped.ends_with('?') {
+            true
+        } else if stripped.ends_with('.') {
+            let stem = stripped
+                .trim_end_matches('.')
+                .trim_start_matches(|c: char| !c.is_alphanumeric())
+                .to_lowercase();
+            !(ABBREVIATIONS.contains(stem.as_str()) || stem.chars().count() == 1)
+        } else {
+            false
+        };
+
+        if ends_sentence && pending {
+            count += 1;
+            pending = false;
+        }
+    }
+
+    if pending {
+        count += 1;
+    }
+
+    count
+}
+
+/// Converts a byte offset in the given line into a count of UTF-16 code units
+///
+/// A character straddling the offset is counted as a whole.
+fn utf16_column(line: &str, byte_offset: usize) -> u32 {
+    line.char_indices()
+        .take_while(|&(index, _)| index < byte_offset)
+        .map(|(_, c)| c.len_utf16() as u32)
+        .sum()
+}
+
+/// Determines if the given character is a strong right-to-left character of the Hebrew or Arabic
+/// blocks
+fn is_strong_rtl(c: char) -> bool {
+    match c as u32 {
+        0x0590..=0x05FF | 0x0600..=0x06FF | 0x0750..=0x077F | 0x08A0..=0x08FF => true,
+        0xFB1D..=0xFB4F | 0xFB50..=0xFDFF | 0xFE70..=0xFEFF => true,
+        _ => false,
+    }
+}
+
+/// Constructs a `Paragraph` holding the given text in a `Strong`
+fn strong_paragraph(text: &str) -> DoogieResult<Node> {
+    let mut paragraph = Node::from_type(NodeType::CMarkNodeParagraph)?;
+    let mut strong = Node::from_type(NodeType::CMarkNodeStrong)?;
+    let mut content = Node::from_type(NodeType::CMarkNodeText)?;
+
+    if let Node::Text(ref mut content) = content {
+        content.set_content(&text.to_string())?;
+    }
+
+    strong.append_child(&mut content)?;
+    paragraph.append_child(&mut strong)?;
+
+    Ok(paragraph)
+}
+
+/// Replaces the trailing spaces of the hard line breaks of rendered CommonMark with backslashes
+///
+/// Lines belonging to a `CodeBlock` or `HtmlBlock` of the reparsed text are not rewritten.
+fn backslash_hardbreaks(rendered: &str) -> DoogieResult<String> {
+    let reparsed = parse_bytes_with_options(rendered.as_bytes(), &ParseOptions::default())?;
+    let mut literal_lines = HashSet::new();
+    for (node, event) in reparsed.iter() {
+        if event != IterEventType::Enter {
+            continue;
+        }
+
+        match node {
+            Node::CodeBlock(_) | Node::HtmlBlock(_) => {
+                literal_lines.extend(node.get_start_line()..=node.get_end_line())
+            }
+            _ => (),
+        }
+    }
+
+    let mut lines = Vec::new();
+    for (index, line) in rendered.split('\n').enumerate() {
+        let rest = line.trim_start_matches(|c| c == ' ' || c == '>');
+        if literal_lines.contains(&(index as u32 + 1))
+            || !line.ends_with("  ")
+            || rest.trim().is_empty()
+        {
+            lines.push(line.to_string());
+            continue;
+        }
+
+        lines.push(format!("{}\\", line.trim_end_matches(' ')));
+    }
+
+    Ok(lines.join("\n"))
+}
+
+/// Splits the given buffer into its leading front matter, if any, and the remaining body
+///
+/// An opening `---` followed by a blank line is a thematic break rather than front matter.
+fn split_front_matter(buffer: &str) -> (Option<&str>, &str) {
+    let mut lines = buffer.split('\n').peekable();
+    match lines.next() {
+        Some(first) if first.trim_end() == "---" => (),
+        _ => return (None, buffer),
+    }
+    match lines.peek() {
+        Some(second) if !second.trim().is_empty() => (),
+        _ => return (None, buffer),
+    }
+
+    let start = buffer.find('\n').map(|index| index + 1).unwrap_or(buffer.len());
+    let mut offset = start;
+    for line in lines {
+        let end = offset + line.len();
+        let trimmed = line.trim_end();
+        if trimmed == "---" || trimmed == "..." {
+            let body_start = (end + 1).min(buffer.len());
+            return (Some(&buffer[start..offset]), &buffer[body_start..]);
+        }
+        offset = end + 1;
+    }
+
+    (None, buffer)
+}
+
+/// Counts the whitespace separated words of the given text that contain an alphanumeric character
+fn count_words(text: &str) -> usize {
+    text.split_whitespace()
+        .filter(|word| word.chars().any(|c| c.is_alphanumeric()))
+        .count()
+}
+
+/// Returns the file extension of the last path segment of the given URL, or an empty string
+fn url_extension(url: &str) -> &str {
+    let path = url.split(|c| c == '?' || c == '#').next().unwrap_or("");
+    let file_name = path.rsplit('/').next().unwrap_or("");
+
+    match file_name.rfind('.') {
+        Some(index) if index > 0 => &file_name[index + 1..],
+        _ => "",
+    }
+}
+
+/// A document that can be moved between threads
+///
+/// A `Node` cannot be sent to another thread: it refers to libcmark memory that is not
+/// synchronized and shares ownership of it through `Rc`. A `SendableDocument` instead holds the
+/// document rendered as CommonMark, which is parsed again on the receiving thread. Anything not
+/// preserved by rendering, such as source positions, is lost on the way.
+///
+/// # Examples
+///
+/// ```
+/// use doogie::{parse_document, SendableDocument};
+/// use std::thread;
+///
+/// let worker = thread::spawn(|| {
+///     let root = parse_document("# Title\n\nSome text\n");
+///     SendableDocument::from_node(&root)
+/// });
+///
+/// let root = worker.join().unwrap().into_node().unwrap();
+///
+/// assert_eq!(root.render_commonmark(), "# Title\n\nSome text\n");
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct SendableDocument {
+    /// The document rendered as CommonMark.
+    commonmark: String,
+}
+
+impl SendableDocument {
+    /// Captures the subtree rooted at the given `Node`
+    pub fn from_node(node: &Node) -> Self {
+        SendableDocument {
+            commonmark: node.render_commonmark(),
+        }
+    }
+
+    /// Returns the CommonMark form of the document
+    pub fn as_str(&self) -> &str {
+        &self.commonmark
+    }
+
+    /// Parses the document into a new tree owned by the current thread
+    pub fn into_node(self) -> DoogieResult<Node> {
+        parse_document_checked(&self.commonmark)
+    }
+}
+
+/// Incremental CommonMark parser for documents that are read in chunks
+///
+/// The chunks may split the document anywhere, even within a line. Unlike
+/// `parse_document_preserving_markers`, the bullet markers of the source are not recorded since
+/// the whole source is never held.
+///
+/// # Examples
+///
+/// ```
+/// use doogie::StreamingParser;
+///
+/// let mut parser = StreamingParser::new();
+/// parser.feed(b"# My Great ");
+/// parser.feed(b"Document\n\n* Item 1\n");
+///
+/// let root = parser.finish();
+/// ```
+pub struct StreamingParser {
+    parser: *mut CMarkParserPtr,
+}
+
+impl StreamingParser {
+    /// Constructs a new `StreamingParser` using the default options
+    pub fn new() -> Self {
+        Self::with_options(&ParseOptions::default())
+    }
+
+    /// Constructs a new `StreamingParser` using the given options
+    ///
+    /// Panics if libcmark cannot allocate the parser.
+    pub fn with_options(options: &ParseOptions) -> Self {
+        let parser: *mut CMarkParserPtr;
+        unsafe {
+            parser = cmark_parser_new(u32::from(options) as c_int);
+        }
+        if parser.is_null() {
+            panic!("libcmark could not allocate a parser");
+        }
+
+        StreamingParser { parser }
+    }
+
+    /// Feeds the next chunk of the document to the parser
+    pub fn feed(&mut self, chunk: &[u8]) {
+        unsafe {
+            cmark_parser_feed(self.parser, chunk.as_ptr(), chunk.len() as size_t);
+        }
+    }
+
+    /// Finishes parsing and returns the root node of the document tree
+    pub fn finish(self) -> Node {
+        let root_ptr: *mut CMarkNodePtr;
+        unsafe {
+            root_ptr = cmark_parser_finish(self.parser);
+        }
+
+        document_from_parsed_root(root_ptr).expect("libcmark did not produce a Document root")
+    }
+}
+
+impl Default for StreamingParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for StreamingParser {
+    /// Frees the underlying libcmark parser
+    fn drop(&mut self) {
+        unsafe {
+            cmark_parser_free(self.parser);
+        }
+    }
+}
+
+/// Typed visitor over the `Node`s of a document subtree, driven by `Node::accept`
+///
+/// Each method is called on entering and on exiting a `Node` of the corresponding type, the event
+/// telling which. Leaf nodes, such as `Text`, `Code` or `ThematicBreak`, are only entered. All
+/// methods do nothing by default, so implementors only override those they are interested in.
+///
+/// # Examples
+///
+/// ```
+/// use doogie::constants::IterEventType;
+/// use doogie::{parse_document, Node, Visitor};
+///
+/// struct HeadingCounter {
+///     count: usize,
+/// }
+///
+/// impl Visitor for HeadingCounter {
+///     fn visit_heading(&mut self, _node: &Node, event: &IterEventType) {
+///         if *event == IterEventType::Enter {
+///             self.count += 1;
+///         }
+///     }
+/// }
+///
+/// let root = parse_document("# One\n\n## Two\n");
+/// let mut counter = HeadingCounter { count: 0 };
+/// root.accept(&mut counter);
+///
+/// assert_eq!(counter.count, 2);
+/// ```
+pub trait Visitor {
+    /// Visits a `Document` `Node`
+    fn visit_document(&mut self, _node: &Node, _event: &IterEventType) {}
+
+    /// Visits a `BlockQuote` `Node`
+    fn visit_block_quote(&mut self, _node: &Node, _event: &IterEventType) {}
+
+    /// Visits a `List` `Node`
+    fn visit_list(&mut self, _node: &Node, _event: &IterEventType) {}
+
+    /// Visits an `Item` `Node`
+    fn visit_item(&mut self, _node: &Node, _event: &IterEventType) {}
+
+    /// Visits a `CodeBlock` `Node`
+    fn visit_code_block(&mut self, _node: &Node, _event: &IterEventType) {}
+
+    /// Visits an `HtmlBlock` `Node`
+    fn visit_html_block(&mut self, _node: &Node, _event: &IterEventType) {}
+
+    /// Visits a `CustomBlock` `Node`
+    fn visit_custom_block(&mut self, _node: &Node, _event: &IterEventType) {}
+
+    /// Visits a `Paragraph` `Node`
+    fn visit_paragraph(&mut self, _node: &Node, _event: &IterEventType) {}
+
+    /// Visits a `Heading` `Node`
+    fn visit_heading(&mut self, _node: &Node, _event: &IterEventType) {}
+
+    /// Visits a `ThematicBreak` `Node`
+    fn visit_thematic_break(&mut self, _node: &Node, _event: &IterEventType) {}
+
+    /// Visits a `Text` `Node`
+    fn visit_text(&mut self, _node: &Node, _event: &IterEventType) {}
+
+    /// Visits a `SoftBreak` `Node`
+    fn visit_soft_break(&mut self, _node: &Node, _event: &IterEventType) {}
+
+    /// Visits a `LineBreak` `Node`
+    fn visit_line_break(&mut self, _node: &Node, _event: &IterEventType) {}
+
+    /// Visits a `Code` `Node`
+    fn visit_code(&mut self, _node: &Node, _event: &IterEventType) {}
+
+    /// Visits an `HtmlInline` `Node`
+    fn visit_html_inline(&mut self, _node: &Node, _event: &IterEventType) {}
+
+    /// Visits a `CustomInline` `Node`
+    fn visit_custom_inline(&mut self, _node: &Node, _event: &IterEventType) {}
+
+    /// Visits an `Emph` `Node`
+    fn visit_emph(&mut self, _node: &Node, _event: &IterEventType) {}
+
+    /// Visits a `Strong` `Node`
+    fn visit_strong(&mut self, _node: &Node, _event: &IterEventType) {}
+
+    /// Visits a `Link` `Node`
+    fn visit_link(&mut self, _node: &Node, _event: &IterEventType) {}
+
+    /// Visits an `Image` `Node`
+    fn visit_image(&mut self, _node: &Node, _event: &IterEventType) {}
+}
+
+/// Writes a buffer rendered by libcmark to the given writer and frees it
+///
+/// Returns `DoogieError::NodeNone` if libcmark did not return a buffer.
+fn write_rendered<W: Write>(rendered: *const c_char, writer: &mut W) -> DoogieResult<()> {
+    if rendered.is_null() {
+        return Err(DoogieError::NodeNone);
+    }
+
+    let result;
+    unsafe {
+        result = writer.write_all(CStr::from_ptr(rendered).to_bytes());
+        free(rendered as *mut c_void);
+    }
+
+    Ok(result?)
+}
+
+/// Manages the memory resources of `Node` instances.
+///
+/// Tracking and untracking roots are constant time operations, so unlinking and moving many nodes
+/// stays linear in the number of nodes moved.
+#[derive(Debug)]
+struct ResourceManager {
+    roots: RefCell<HashSet<*mut CMarkNodePtr>>,
+}
+
+impl Drop for ResourceManager {
+    fn drop(&mut self) {
+        let roots = self.roots.borrow();
+        for pointer in roots.iter() {
+            forget_list_markers(*pointer);
+            unsafe {
+                cmark_node_free(*pointer);
+            }
+        }
+    }
+}
+
+impl ResourceManager {
+    /// Construct a new ResourceManager instance.
+    pub fn new() -> ResourceManager {
+        ResourceManager {
+            roots: RefCell::new(HashSet::new()),
+        }
+    }
+
+    /// Tracks the given pointer as a root Node of some tree or subtree
+    pub fn track_root(&self, pointer: &*mut CMarkNodePtr) {
+        self.roots.borrow_mut().insert(*pointer);
+    }
+
+    /// Removes the tracking for a given pointer
+    pub fn untrack_root(&self, pointer: &*mut CMarkNodePtr) {
+        self.roots.borrow_mut().remove(pointer);
+    }
+
+    #[cfg(test)]
+    /// Determines if the given pointer is currently being tracked
+    pub fn is_tracking(&self, pointer: &*mut CMarkNodePtr) -> bool {
+        let roots = self.roots.borrow();
+        roots.contains(pointer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        cmark_node_get_type, cmark_node_new, extract_plain_text, parse_bytes, parse_document,
+        parse_document_checked, parse_document_preserving_markers, parse_document_with_options,
+        parse_file, parse_reader, parse_with_front_matter, CMarkNodePtr, CodeBlock, FormattedRun,
+        IterEventType, List, LspPosition, Node, NodeIterator, NodeResource, NodeType,
+        SendableDocument, StreamingParser, Text, Visitor, LIST_MARKERS,
+    };
+    use builder::DocumentBuilder;
+    use constants::*;
+    use errors::DoogieError;
+    use proptest::prelude::*;
+    #[cfg(feature = "serde")]
+    use serde_json;
+    #[cfg(feature = "serde")]
+    use serialization::SerializedNode;
+    use std::collections::HashSet;
+    use std::env;
+    use std::ptr;
+    use std::fs;
+    use std::io::{self, Cursor, Write};
+    use std::thread;
+    use try_from::TryFrom;
+
+    /// Returns some arbitrary alphanumeric textual content
+    fn arb_content(max_words: usize) -> BoxedStrategy<String> {
+        prop::collection::vec("[[:alnum:]]{1,45}", 1..max_words)
+            .prop_map(|v| v.join(" "))
+            .boxed()
+    }
+
+    #[test]
+    fn test_parse_document() {
+        let body = "\
+        # My New Document
+        ";
+        let node = parse_document(body);
+
+        match node {
+            Node::Document(_) => (),
+            _ => panic!("Did not get a Document Node after parsing."),
+        }
+    }
+
+    #[test]
+    fn test_equality() {
+        let body = "\
+        # My New Document
+        ";
+        let node = parse_document(body);
+        let other = node.itself().unwrap();
+
+        assert_eq!(node, other);
+    }
+
+    #[test]
+    fn test_inequality() {
+        let body = "\
+        # My New Document
+        ";
+        let node = parse_document(body);
+        let other = node.first_child()
+            .unwrap()
+            .expect("Root should have a child");
+
+        assert_ne!(node, other);
+    }
+
+    #[test]
+    fn test_root_node_gets_tracked() {
+        let body = "\
+        # My New Document
+        ";
+        let manager;
+        let pointer;
+        {
+            let node = parse_document(body);
+            manager = node.manager();
+            pointer = node.pointer();
+        }
+        assert!(manager.roots.borrow().contains(&pointer));
+    }
+
+    #[test]
+    fn test_iterator_hits_all_items() {
+        let body = "* Item 1\n* Item 2\n* Item 3";
+        let root = parse_document(body);
+        let mut node_contents: Vec<String> = Vec::new();
+        let mut item_count = 0;
+
+        for item in root.iter() {
+            match item {
+                (Node::Item(_), IterEventType::Enter) => item_count += 1,
+                (Node::Text(ref text), IterEventType::Enter) => {
+                    node_contents.push(text.get_content().unwrap())
+                }
+                _ => (),
+            }
+        }
+
+        assert_eq!(item_count, 3);
+        assert!(node_contents.contains(&String::from("Item 1")));
+        assert!(node_contents.contains(&String::from("Item 2")));
+        assert!(node_contents.contains(&String::from("Item 3")));
+    }
+
+    #[test]
+    fn test_parent_child_traversal() {
+        let body = "* Item 1\n* Item 2\n* Item 3";
+        let root = parse_document(body);
+        let child = root.first_child()
+            .unwrap()
+            .expect("Root should have had child");
+        assert_eq!(
+            root,
+            child
+                .parent()
+                .unwrap()
+                .expect("Child should have had a parent")
+        );
+    }
+
+    #[test]
+    fn test_sibling_traversal() {
+        let body = "* Item 1\n* Item 2\n* Item 3";
+        let root = parse_document(body);
+        let list = root.first_child()
+            .unwrap()
+            .expect("Root should have had list");
+        let first_item = list.first_child()
+            .unwrap()
+            .expect("List should have had item");
+        let next_item = first_item
+            .next_sibling()
+            .unwrap()
+            .expect("First item should have had next sibling");
+
+        assert_eq!(
+            first_item,
+            next_item
+                .prev_sibling()
+                .unwrap()
+                .expect("Next item should have had prev item")
+        );
+    }
+
+    #[test]
+    fn parse_and_render() {
+        let content = "# Testing";
+        let root = parse_document(content);
+
+        assert_eq!(content, root.render_commonmark().trim());
+    }
+
+    #[test]
+    fn test_from_raw() {
+        let node_pointer: *mut CMarkNodePtr;
+        unsafe {
+            node_pointer = cmark_node_new(NodeType::CMarkNodeParagraph as u32);
+        }
+
+        let node = Node::from_raw(node_pointer).unwrap();
+
+        match node {
+            Node::Paragraph(_) => (),
+            _ => panic!("Node should have been a paragraph"),
+        }
+    }
+
+    #[test]
+    fn test_unlink() {
+        let body = "* Item 1\n* Item 2\n* Item 3";
+        let root = parse_document(body);
+        let mut first_item = root.first_child()
+            .unwrap()
+            .expect("Root should have first child")
+            .first_child()
+            .unwrap()
+            .expect("List should have first item");
+        let manager = first_item.manager();
+
+        first_item.unlink();
+
+        assert!(manager.roots.borrow().contains(&first_item.pointer()));
+        for (node, _) in root.iter() {
+            if let Node::Text(node) = node {
+                assert!(!node.get_content().unwrap().contains("Item 1"));
+            }
+        }
+    }
+
+    #[test]
+    fn test_append_child() {
+        let mut root_node = Node::from_type(NodeType::CMarkNodeDocument).unwrap();
+        let mut child_node = Node::from_type(NodeType::CMarkNodeParagraph).unwrap();
+
+        root_node.append_child(&mut child_node).unwrap();
+
+        assert!(!root_node.manager().is_tracking(&child_node.pointer()));
+        assert_eq!(
+            root_node
+                .first_child()
+                .unwrap()
+                .expect("Root should have child"),
+            child_node
+        );
+    }
+
+    #[test]
+    fn test_document_children() {
+        for i in 1..21 {
+            let node_type = NodeType::CMarkNodeDocument;
+            let other_type = NodeType::try_from(i).unwrap();
+            let mut node = Node::from_type(node_type).unwrap();
+            let mut child = Node::from_type(other_type.clone()).unwrap();
+            match node.can_append_child(&child).unwrap() {
+                true => assert!(
+                    DOCUMENT_CHILDREN.contains(&other_type),
+                    "{:?} should not have been a valid block quote child, but was",
+                    other_type
+                ),
+                false => assert!(
+                    !DOCUMENT_CHILDREN.contains(&other_type),
+                    "{:?} should be a valid block quote child, but was not",
+                    other_type
+                ),
+            }
+            match node.append_child(&mut child) {
+                Err(_) => assert!(
+                    !DOCUMENT_CHILDREN.contains(&other_type),
+                    "{:?} should be able to append, but was not",
+                    other_type
+                ),
+                Ok(_) => assert!(
+                    DOCUMENT_CHILDREN.contains(&other_type),
+                    "{:?} should not have been able to append, but was",
+                    other_type
+                ),
+            }
+        }
+    }
+
+    #[test]
+    fn test_block_quote_children() {
+        for i in 1..21 {
+            let node_type = NodeType::CMarkNodeBlockQuote;
+            let other_type = NodeType::try_from(i).unwrap();
+            let mut node = Node::from_type(node_type).unwrap();
+            let mut child = Node::from_type(other_type.clone()).unwrap();
+            match node.can_append_child(&child).unwrap() {
+                true => assert!(
+                    BLOCK_QUOTE_CHILDREN.contains(&other_type),
+                    "{:?} should not have been a valid block quote child, but was",
+                    other_type
+                ),
+                false => assert!(
+                    !BLOCK_QUOTE_CHILDREN.contains(&other_type),
+                    "{:?} should be a valid block quote child, but was not",
+                    other_type
+                ),
+            }
+            match node.append_child(&mut child) {
+                Err(_) => assert!(
+                    !BLOCK_QUOTE_CHILDREN.contains(&other_type),
+                    "{:?} should be able to append, but was not",
+                    other_type
+                ),
+                Ok(_) => assert!(
+                    BLOCK_QUOTE_CHILDREN.contains(&other_type),
+                    "{:?} should not have been able to append, but was",
+                    other_type
+                ),
+            }
+        }
+    }
+
+    #[test]
+    fn test_list_children() {
+        for i in 1..21 {
+            let node_type = NodeType::CMarkNodeList;
+            let other_type = NodeType::try_from(i).unwrap();
+            let mut node = Node::from_type(node_type).unwrap();
+            let mut child = Node::from_type(other_type.clone()).unwrap();
+            match node.can_append_child(&child).unwrap() {
+                true => assert!(
+                    LIST_CHILDREN.contains(&other_type),
+                    "{:?} should not have been a valid block quote child, but was",
+                    other_type
+                ),
+                false => assert!(
+                    !LIST_CHILDREN.contains(&other_type),
+                    "{:?} should be a valid block quote child, but was not",
+                    other_type
+                ),
+            }
+            match node.append_child(&mut child) {
+                Err(_) => assert!(
+                    !LIST_CHILDREN.contains(&other_type),
+                    "{:?} should be able to append, but was not",
+                    other_type
+                ),
+                Ok(_) => assert!(
+                    LIST_CHILDREN.contains(&other_type),
+                    "{:?} should not have been able to append, but was",
+                    other_type
+                ),
+            }
+        }
+    }
+
+    #[test]
+    fn test_item_children() {
+        for i in 1..21 {
+            let node_type = NodeType::CMarkNodeItem;
+            let other_type = NodeType::try_from(i).unwrap();
+            let mut node = Node::from_type(node_type).unwrap();
+            let mut child = Node::from_type(other_type.clone()).unwrap();
+            match node.can_append_child(&child).unwrap() {
+                true => assert!(
+                    ITEM_CHILDREN.contains(&other_type),
+                    "{:?} should not have been a valid block quote child, but was",
+                    other_type
+                ),
+                false => assert!(
+                    !ITEM_CHILDREN.contains(&other_type),
+                    "{:?} should be a valid block quote child, but was not",
+                    other_type
+                ),
+            }
+            match node.append_child(&mut child) {
+                Err(_) => assert!(
+                    !ITEM_CHILDREN.contains(&other_type),
+                    "{:?} should be able to append, but was not",
+                    other_type
+                ),
+                Ok(_) => assert!(
+                    ITEM_CHILDREN.contains(&other_type),
+                    "{:?} should not have been able to append, but was",
+                    other_type
+                ),
+            }
+        }
+    }
+
+    #[test]
+    fn test_code_block_children() {
+        for i in 1..21 {
+            let node_type = NodeType::CMarkNodeCodeBlock;
+            let other_type = NodeType::try_from(i).unwrap();
+            let mut node = Node::from_type(node_type).unwrap();
+            let mut child = Node::from_type(other_type.clone()).unwrap();
+            match node.can_append_child(&child).unwrap() {
+                true => assert!(
+                    CODE_BLOCK_CHILDREN.contains(&other_type),
+                    "{:?} should not have been a valid block quote child, but was",
+                    other_type
+                ),
+                false => assert!(
+                    !CODE_BLOCK_CHILDREN.contains(&other_type),
+                    "{:?} should be a valid block quote child, but was not",
+                    other_type
+                ),
+            }
+            match node.append_child(&mut child) {
+                Err(_) => assert!(
+                    !CODE_BLOCK_CHILDREN.contains(&other_type),
+                    "{:?} should be able to append, but was not",
+                    other_type
+                ),
+                Ok(_) => assert!(
+                    CODE_BLOCK_CHILDREN.contains(&other_type),
+                    "{:?} should not have been able to append, but was",
+                    other_type
+                ),
+            }
+        }
+    }
+
+    #[test]
+    fn test_html_block_children() {
+        for i in 1..21 {
+            let node_type = NodeType::CMarkNodeHtmlBlock;
+            let other_type = NodeType::try_from(i).unwrap();
+            let mut node = Node::from_type(node_type).unwrap();
+            let mut child = Node::from_type(other_type.clone()).unwrap();
+            match node.can_append_child(&child).unwrap() {
+                true => assert!(
+                    HTML_BLOCK_CHILDREN.contains(&other_type),
+                    "{:?} should not have been a valid block quote child, but was",
+                    other_type
+                ),
+                false => assert!(
+                    !HTML_BLOCK_CHILDREN.contains(&other_type),
+                    "{:?} should be a valid block quote child, but was not",
+                    other_type
+                ),
+            }
+            match node.append_child(&mut child) {
+                Err(_) => assert!(
+                    !HTML_BLOCK_CHILDREN.contains(&other_type),
+                    "{:?} should be able to append, but was not",
+                    other_type
+                ),
+                Ok(_) => assert!(
+                    HTML_BLOCK_CHILDREN.contains(&other_type),
+                    "{:?} should not have been able to append, but was",
+                    other_type
+                ),
+            }
+        }
+    }
+
+    #[test]
+    fn test_custom_block_children() {
+        for i in 1..21 {
+            let node_type = NodeType::CMarkNodeCustomBlock;
+            let other_type = NodeType::try_from(i).unwrap();
+            let mut node = Node::from_type(node_type).unwrap();
+            let mut child = Node::from_type(other_type.clone()).unwrap();
+            match node.can_append_child(&child).unwrap() {
+                true => assert!(
+                    CUSTOM_BLOCK_CHILDREN.contains(&other_type),
+                    "{:?} should not have been a valid block quote child, but was",
+                    other_type
+                ),
+                false => assert!(
+                    !CUSTOM_BLOCK_CHILDREN.contains(&other_type),
+                    "{:?} should be a valid block quote child, but was not",
+                    other_type
+                ),
+            }
+            match node.append_child(&mut child) {
+                Err(_) => assert!(
+                    !CUSTOM_BLOCK_CHILDREN.contains(&other_type),
+                    "{:?} should be able to append, but was not",
+                    other_type
+                ),
+                Ok(_) => assert!(
+                    CUSTOM_BLOCK_CHILDREN.contains(&other_type),
+                    "{:?} should not have been able to append, but was",
+                    other_type
+                ),
+            }
+        }
+    }
+
+    #[test]
+    fn test_paragraph_children() {
+        for i in 1..21 {
+            let node_type = NodeType::CMarkNodeParagraph;
+            let other_type = NodeType::try_from(i).unwrap();
+            let mut node = Node::from_type(node_type).unwrap();
+            let mut child = Node::from_type(other_type.clone()).unwrap();
+            match node.can_append_child(&child).unwrap() {
+                true => assert!(
+                    PARAGRAPH_CHILDREN.contains(&other_type),
+                    "{:?} should not have been a valid block quote child, but was",
+                    other_type
+                ),
+                false => assert!(
+                    !PARAGRAPH_CHILDREN.contains(&other_type),
+                    "{:?} should be a valid block quote child, but was not",
+                    other_type
+                ),
+            }
+            match node.append_child(&mut child) {
+                Err(_) => assert!(
+                    !PARAGRAPH_CHILDREN.contains(&other_type),
+                    "{:?} should be able to append, but was not",
+                    other_type
+                ),
+                Ok(_) => assert!(
+                    PARAGRAPH_CHILDREN.contains(&other_type),
+                    "{:?} should not have been able to append, but was",
+                    other_type
+                ),
+            }
+        }
+    }
+
+    #[test]
+    fn test_heading_children() {
+        for i in 1..21 {
+            let node_type = NodeType::CMarkNodeHeading;
+            let other_type = NodeType::try_from(i).unwrap();
+            let mut node = Node::from_type(node_type).unwrap();
+            let mut child = Node::from_type(other_type.clone()).unwrap();
+            match node.can_append_child(&child).unwrap() {
+                true => assert!(
+                    HEADING_CHILDREN.contains(&other_type),
+                    "{:?} should not have been a valid block quote child, but was",
+                    other_type
+                ),
+                false => assert!(
+                    !HEADING_CHILDREN.contains(&other_type),
+                    "{:?} should be a valid block quote child, but was not",
+                    other_type
+                ),
+            }
+            match node.append_child(&mut child) {
+                Err(_) => assert!(
+                    !HEADING_CHILDREN.contains(&other_type),
+                    "{:?} should be able to append, but was not",
+                    other_type
+                ),
+                Ok(_) => assert!(
+                    HEADING_CHILDREN.contains(&other_type),
+                    "{:?} should not have been able to append, but was",
+                    other_type
+                ),
+            }
+        }
+    }
+
+    #[test]
+    fn test_thematic_break_children() {
+        for i in 1..21 {
+            let node_type = NodeType::CMarkNodeThematicBreak;
+            let other_type = NodeType::try_from(i).unwrap();
+            let mut node = Node::from_type(node_type).unwrap();
+            let mut child = Node::from_type(other_type.clone()).unwrap();
+            match node.can_append_child(&child).unwrap() {
+                true => assert!(
+                    THEMATIC_BREAK_CHILDREN.contains(&other_type),
+                    "{:?} should not have been a valid block quote child, but was",
+                    other_type
+                ),
+                false => assert!(
+                    !THEMATIC_BREAK_CHILDREN.contains(&other_type),
+                    "{:?} should be a valid block quote child, but was not",
+                    other_type
+                ),
+            }
+            match node.append_child(&mut child) {
+                Err(_) => assert!(
+                    !THEMATIC_BREAK_CHILDREN.contains(&other_type),
+                    "{:?} should be able to append, but was not",
+                    other_type
+                ),
+                Ok(_) => assert!(
+                    THEMATIC_BREAK_CHILDREN.contains(&other_type),
+                    "{:?} should not have been able to append, but was",
+                    other_type
+                ),
+            }
+        }
+    }
+
+    #[test]
+    fn test_text_children() {
+        for i in 1..21 {
+            let node_type = NodeType::CMarkNodeText;
+            let other_type = NodeType::try_from(i).unwrap();
+            let mut node = Node::from_type(node_type).unwrap();
+            let mut child = Node::from_type(other_type.clone()).unwrap();
+            match node.can_append_child(&child).unwrap() {
+                true => assert!(
+                    TEXT_CHILDREN.contains(&other_type),
+                    "{:?} should not have been a valid block quote child, but was",
+                    other_type
+                ),
+                false => assert!(
+                    !TEXT_CHILDREN.contains(&other_type),
+                    "{:?} should be a valid block quote child, but was not",
+                    other_type
+                ),
+            }
+            match node.append_child(&mut child) {
+                Err(_) => assert!(
+                    !TEXT_CHILDREN.contains(&other_type),
+                    "{:?} should be able to append, but was not",
+                    other_type
+                ),
+                Ok(_) => assert!(
+                    TEXT_CHILDREN.contains(&other_type),
+                    "{:?} should not have been able to append, but was",
+                    other_type
+                ),
+            }
+        }
+    }
+
+    #[test]
+    fn test_soft_break_children() {
+        for i in 1..21 {
+            let node_type = NodeType::CMarkNodeSoftbreak;
+            let other_type = NodeType::try_from(i).unwrap();
+            let mut node = Node::from_type(node_type).unwrap();
+            let mut child = Node::from_type(other_type.clone()).unwrap();
+            match node.can_append_child(&child).unwrap() {
+                true => assert!(
+                    SOFT_BREAK_CHILDREN.contains(&other_type),
+                    "{:?} should not have been a valid block quote child, but was",
+                    other_type
+                ),
+                false => assert!(
+                    !SOFT_BREAK_CHILDREN.contains(&other_type),
+                    "{:?} should be a valid block quote child, but was not",
+                    other_type
+                ),
+            }
+            match node.append_child(&mut child) {
+                Err(_) => assert!(
+                    !SOFT_BREAK_CHILDREN.contains(&other_type),
+                    "{:?} should be able to append, but was not",
+                    other_type
+                ),
+                Ok(_) => assert!(
+                    SOFT_BREAK_CHILDREN.contains(&other_type),
+                    "{:?} should not have been able to append, but was",
+                    other_type
+                ),
+            }
+        }
+    }
+
+    #[test]
+    fn test_line_break_children() {
+        for i in 1..21 {
+            let node_type = NodeType::CMarkNodeLinebreak;
+            let other_type = NodeType::try_from(i).unwrap();
+            let mut node = Node::from_type(node_type).unwrap();
+            let mut child = Node::from_type(other_type.clone()).unwrap();
+            match node.can_append_child(&child).unwrap() {
+                true => assert!(
+                    LINE_BREAK_CHILDREN.contains(&other_type),
+                    "{:?} should not have been a valid block quote child, but was",
+                    other_type
+                ),
+                false => assert!(
+                    !LINE_BREAK_CHILDREN.contains(&other_type),
+                    "{:?} should be a valid block quote child, but was not",
+                    other_type
+                ),
+            }
+            match node.append_child(&mut child) {
+                Err(_) => assert!(
+                    !LINE_BREAK_CHILDREN.contains(&other_type),
+                    "{:?} should be able to append, but was not",
+                    other_type
+                ),
+                Ok(_) => assert!(
+                    LINE_BREAK_CHILDREN.contains(&other_type),
+                    "{:?} should not have been able to append, but was",
+                    other_type
+                ),
+            }
+        }
+    }
+
+    #[test]
+    fn test_code_children() {
+        for i in 1..21 {
+            let node_type = NodeType::CMarkNodeCode;
+            let other_type = NodeType::try_from(i).unwrap();
+            let mut node = Node::from_type(node_type).unwrap();
+            let mut child = Node::from_type(other_type.clone()).unwrap();
+            match node.can_append_child(&child).unwrap() {
+                true => assert!(
+                    CODE_CHILDREN.contains(&other_type),
+                    "{:?} should not have been a valid block quote child, but was",
+                    other_type
+                ),
+                false => assert!(
+                    !CODE_CHILDREN.contains(&other_type),
+                    "{:?} should be a valid block quote child, but was not",
+                    other_type
+                ),
+            }
+            match node.append_child(&mut child) {
+                Err(_) => assert!(
+                    !CODE_CHILDREN.contains(&other_type),
+                    "{:?} should be able to append, but was not",
+                    other_type
+                ),
+                Ok(_) => assert!(
+                    CODE_CHILDREN.contains(&other_type),
+                    "{:?} should not have been able to append, but was",
+                    other_type
+                ),
+            }
+        }
+    }
+
+    #[test]
+    fn test_inline_html_children() {
+        for i in 1..21 {
+            let node_type = NodeType::CMarkNodeHtmlInline;
+            let other_type = NodeType::try_from(i).unwrap();
+            let mut node = Node::from_type(node_type).unwrap();
+            let mut child = Node::from_type(other_type.clone()).unwrap();
+            match node.can_append_child(&child).unwrap() {
+                true => assert!(
+                    INLINE_HTML_CHILDREN.contains(&other_type),
+                    "{:?} should not have been a valid block quote child, but was",
+                    other_type
+                ),
+                false => assert!(
+                    !INLINE_HTML_CHILDREN.contains(&other_type),
+                    "{:?} should be a valid block quote child, but was not",
+                    other_type
+                ),
+            }
+            match node.append_child(&mut child) {
+                Err(_) => assert!(
+                    !INLINE_HTML_CHILDREN.contains(&other_type),
+                    "{:?} should be able to append, but was not",
+                    other_type
+                ),
+                Ok(_) => assert!(
+                    INLINE_HTML_CHILDREN.contains(&other_type),
+                    "{:?} should not have been able to append, but was",
+                    other_type
+                ),
+            }
+        }
+    }
+
+    #[test]
+    fn test_custom_inline_children() {
+        for i in 1..21 {
+            let node_type = NodeType::CMarkNodeCustomInline;
+            let other_type = NodeType::try_from(i).unwrap();
+            let mut node = Node::from_type(node_type).unwrap();
+            let mut child = Node::from_type(other_type.clone()).unwrap();
+            match node.can_append_child(&child).unwrap() {
+                true => assert!(
+                    CUSTOM_INLINE_CHILDREN.contains(&other_type),
+                    "{:?} should not have been a valid block quote child, but was",
+                    other_type
+                ),
+                false => assert!(
+                    !CUSTOM_INLINE_CHILDREN.contains(&other_type),
+                    "{:?} should be a valid block quote child, but was not",
+                    other_type
+                ),
+            }
+            match node.append_child(&mut child) {
+                Err(_) => assert!(
+                    !CUSTOM_INLINE_CHILDREN.contains(&other_type),
+                    "{:?} should be able to append, but was not",
+                    other_type
+                ),
+                Ok(_) => assert!(
+                    CUSTOM_INLINE_CHILDREN.contains(&other_type),
+                    "{:?} should not have been able to append, but was",
+                    other_type
+                ),
+            }
+        }
+    }
+
+    #[test]
+    fn test_emph_children() {
+        for i in 1..21 {
+            let node_type = NodeType::CMarkNodeEmph;
+            let other_type = NodeType::try_from(i).unwrap();
+            let mut node = Node::from_type(node_type).unwrap();
+            let mut child = Node::from_type(other_type.clone()).unwrap();
+            match node.can_append_child(&child).unwrap() {
+                true => assert!(
+                    EMPH_CHILDREN.contains(&other_type),
+                    "{:?} should not have been a valid block quote child, but was",
+                    other_type
+                ),
+                false => assert!(
+                    !EMPH_CHILDREN.contains(&other_type),
+                    "{:?} should be a valid block quote child, but was not",
+                    other_type
+                ),
+            }
+            match node.append_child(&mut child) {
+                Err(_) => assert!(
+                    !EMPH_CHILDREN.contains(&other_type),
+                    "{:?} should be able to append, but was not",
+                    other_type
+                ),
+                Ok(_) => assert!(
+                    EMPH_CHILDREN.contains(&other_type),
+                    "{:?} should not have been able to append, but was",
+                    other_type
+                ),
+            }
+        }
+    }
+
+    #[test]
+    fn test_strong_children() {
+        for i in 1..21 {
+            let node_type = NodeType::CMarkNodeStrong;
+            let other_type = NodeType::try_from(i).unwrap();
+            let mut node = Node::from_type(node_type).unwrap();
+            let mut child = Node::from_type(other_type.clone()).unwrap();
+            match node.can_append_child(&child).unwrap() {
+                true => assert!(
+                    STRONG_CHILDREN.contains(&other_type),
+                    "{:?} should not have been a valid block quote child, but was",
+                    other_type
+                ),
+                false => assert!(
+                    !STRONG_CHILDREN.contains(&other_type),
+                    "{:?} should be a valid block quote child, but was not",
+                    other_type
+                ),
+            }
+            match node.append_child(&mut child) {
+                Err(_) => assert!(
+                    !STRONG_CHILDREN.contains(&other_type),
+                    "{:?} should be able to append, but was not",
+                    other_type
+                ),
+                Ok(_) => assert!(
+                    STRONG_CHILDREN.contains(&other_type),
+                    "{:?} should not have been able to append, but was",
+                    other_type
+                ),
+            }
+        }
+    }
+
+    #[test]
+    fn test_link_children() {
+        for i in 1..21 {
+            let node_type = NodeType::CMarkNodeLink;
+            let other_type = NodeType::try_from(i).unwrap();
+            let mut node = Node::from_type(node_type).unwrap();
+            let mut child = Node::from_type(other_type.clone()).unwrap();
+            match node.can_append_child(&child).unwrap() {
+                true => assert!(
+                    LINK_CHILDREN.contains(&other_type),
+                    "{:?} should not have been a valid block quote child, but was",
+                    other_type
+                ),
+                false => assert!(
+                    !LINK_CHILDREN.contains(&other_type),
+                    "{:?} should be a valid block quote child, but was not",
+                    other_type
+                ),
+            }
+            match node.append_child(&mut child) {
+                Err(_) => assert!(
+                    !LINK_CHILDREN.contains(&other_type),
+                    "{:?} should be able to append, but was not",
+                    other_type
+                ),
+                Ok(_) => assert!(
+                    LINK_CHILDREN.contains(&other_type),
+                    "{:?} should not have been able to append, but was",
+                    other_type
+                ),
+            }
+        }
+    }
+
+    #[test]
+    fn test_image_children() {
+        for i in 1..21 {
+            let node_type = NodeType::CMarkNodeImage;
+            let other_type = NodeType::try_from(i).unwrap();
+            let mut node = Node::from_type(node_type).unwrap();
+            let mut child = Node::from_type(other_type.clone()).unwrap();
+            match node.can_append_child(&child).unwrap() {
+                true => assert!(
+                    IMAGE_CHILDREN.contains(&other_type),
+                    "{:?} should not have been a valid block quote child, but was",
                     other_type
                 ),
                 false => assert!(
-                    !LINK_CHILDREN.contains(&other_type),
+                    !IMAGE_CHILDREN.contains(&other_type),
                     "{:?} should be a valid block quote child, but was not",
                     other_type
                 ),
             }
-            match node.append_child(&mut child) {
-                Err(_) => assert!(
-                    !LINK_CHILDREN.contains(&other_type),
-                    "{:?} should be able to append, but was not",
-                    other_type
-                ),
-                Ok(_) => assert!(
-                    LINK_CHILDREN.contains(&other_type),
-                    "{:?} should not have been able to append, but was",
-                    other_type
-                ),
+            match node.append_child(&mut child) {
+                Err(_) => assert!(
+                    !IMAGE_CHILDREN.contains(&other_type),
+                    "{:?} should be able to append, but was not",
+                    other_type
+                ),
+                Ok(_) => assert!(
+                    IMAGE_CHILDREN.contains(&other_type),
+                    "{:?} should not have been able to append, but was",
+                    other_type
+                ),
+            }
+        }
+    }
+
+    #[test]
+    fn test_all_urls() {
+        let body = "[a](http://a.com) ![b](b.png) [c](http://a.com) ![d](b.png) [e](http://e.com)";
+        let root = parse_document(body);
+
+        assert_eq!(
+            root.all_urls().unwrap(),
+            vec![
+                String::from("http://a.com"),
+                String::from("b.png"),
+                String::from("http://e.com"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_insert_section_separators() {
+        let body = "# One\n\nFirst\n\n# Two\n\nSecond\n\n# Three";
+        let root = parse_document(body);
+
+        assert_eq!(root.insert_section_separators().unwrap(), 2);
+
+        let types: Vec<NodeType> = root.child_nodes()
+            .unwrap()
+            .iter()
+            .map(|child| child.get_cmark_type().unwrap())
+            .collect();
+        assert_eq!(
+            types,
+            vec![
+                NodeType::CMarkNodeHeading,
+                NodeType::CMarkNodeParagraph,
+                NodeType::CMarkNodeThematicBreak,
+                NodeType::CMarkNodeHeading,
+                NodeType::CMarkNodeParagraph,
+                NodeType::CMarkNodeThematicBreak,
+                NodeType::CMarkNodeHeading,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rendered_line_stats() {
+        let long_line: String = ::std::iter::repeat("x").take(100).collect();
+        let body = format!("# Title\n\n```\n{}\n```\n", long_line);
+        let root = parse_document(&body);
+
+        let (longest, average) = root.rendered_line_stats(RenderFormat::CommonMark).unwrap();
+
+        assert_eq!(longest, 100);
+        assert!(average > 0.0 && average < 100.0);
+    }
+
+    #[test]
+    fn test_merge_split_inline() {
+        let root = parse_document("*start\nend*");
+
+        assert_eq!(root.merge_split_inline().unwrap(), 1);
+
+        let emph = root.first_child()
+            .unwrap()
+            .expect("Root should have a paragraph")
+            .first_child()
+            .unwrap()
+            .expect("Paragraph should have an emph");
+        let children = emph.child_nodes().unwrap();
+        assert_eq!(children.len(), 1);
+        match children[0] {
+            Node::Text(ref text) => assert_eq!(text.get_content().unwrap(), "start end"),
+            _ => panic!("Emph should only contain a single Text node"),
+        }
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let root = parse_document("# Hi");
+        let heading = root.first_child()
+            .unwrap()
+            .expect("Root should have a heading");
+        let text = heading
+            .first_child()
+            .unwrap()
+            .expect("Heading should have text");
+
+        let dot = root.to_dot().unwrap();
+
+        assert!(dot.starts_with("digraph {"));
+        assert!(dot.contains(&format!("n{} [label=\"document\"];", root.get_id())));
+        assert!(dot.contains(&format!("n{} [label=\"heading\"];", heading.get_id())));
+        assert!(dot.contains(&format!("n{} [label=\"text: Hi\"];", text.get_id())));
+        assert!(dot.contains(&format!("n{} -> n{};", root.get_id(), heading.get_id())));
+        assert!(dot.contains(&format!("n{} -> n{};", heading.get_id(), text.get_id())));
+    }
+
+    #[test]
+    fn test_render_html_with_base() {
+        let body = "[rel](docs/page.html) [abs](https://x.com/a) ![img](./img/a.png)";
+        let root = parse_document(body);
+
+        let html = root.render_html_with_base("https://cdn.example.com/").unwrap();
+
+        assert!(html.contains("href=\"https://cdn.example.com/docs/page.html\""));
+        assert!(html.contains("href=\"https://x.com/a\""));
+        assert!(html.contains("src=\"https://cdn.example.com/img/a.png\""));
+        assert!(
+            root.all_urls()
+                .unwrap()
+                .contains(&String::from("docs/page.html"))
+        );
+    }
+
+    #[test]
+    fn test_render_html_with_base_root_relative_and_query() {
+        let root = parse_document("![img](/img.png) [query](?page=2) [path](a/b.html)");
+
+        let html = root.render_html_with_base("https://cdn/docs?page=1").unwrap();
+
+        assert!(html.contains("src=\"https://cdn/img.png\""));
+        assert!(html.contains("href=\"https://cdn/docs?page=2\""));
+        assert!(html.contains("href=\"https://cdn/docs/a/b.html\""));
+    }
+
+    #[test]
+    fn test_count() {
+        let root = parse_document("# A *b*\n\nSome `code` and [link](url)\nnext");
+        let expected = root.iter()
+            .filter(|&(ref node, ref event)| {
+                *event == IterEventType::Enter
+                    && INLINE_TYPES.contains(&node.get_cmark_type().unwrap())
+            })
+            .count();
+
+        assert_eq!(expected, 10);
+        assert_eq!(
+            root.count(|node_type| INLINE_TYPES.contains(&node_type))
+                .unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_insert_toc() {
+        let body = "# Title\n\nIntro text\n\n## Usage\n\n### Details\n\n## Usage\n";
+        let root = parse_document(body);
+
+        if let Node::Document(ref document) = root {
+            document.insert_toc(2).unwrap();
+        }
+
+        let toc = root.first_child()
+            .unwrap()
+            .expect("Root should have a title")
+            .next_sibling()
+            .unwrap()
+            .expect("Title should be followed by the table of contents");
+        match toc {
+            Node::List(_) => (),
+            _ => panic!("Table of contents should be a List"),
+        }
+        assert_eq!(
+            toc.all_urls().unwrap(),
+            vec![
+                String::from("#title"),
+                String::from("#usage"),
+                String::from("#usage-1"),
+            ]
+        );
+        assert!(!toc.to_plain_text().unwrap().contains("Details"));
+    }
+
+    #[test]
+    fn test_validate_content_utf8() {
+        let valid = parse_bytes(b"# Title\n\nsome text\n");
+        let invalid = parse_bytes(b"# Title\n\nsome \xff text\n");
+
+        match valid {
+            Node::Document(ref document) => document.validate_content_utf8().unwrap(),
+            _ => panic!("Did not get a Document Node after parsing."),
+        }
+        match invalid {
+            Node::Document(ref document) => match document.validate_content_utf8() {
+                Err(DoogieError::InvalidUtf8 { line, .. }) => assert_eq!(line, 3),
+                other => panic!("Expected an InvalidUtf8 error, got {:?}", other),
+            },
+            _ => panic!("Did not get a Document Node after parsing."),
+        }
+    }
+
+    #[test]
+    fn test_hardbreaks_to_paragraphs() {
+        let root = parse_document("first line\\\nsecond *line*");
+
+        assert_eq!(root.hardbreaks_to_paragraphs().unwrap(), 1);
+
+        let children = root.child_nodes().unwrap();
+        assert_eq!(children.len(), 2);
+        assert_eq!(children[0].to_plain_text().unwrap(), "first line\n");
+        assert_eq!(children[1].to_plain_text().unwrap(), "second line\n");
+        assert_eq!(
+            children[1]
+                .last_child()
+                .unwrap()
+                .expect("Second paragraph should have content")
+                .get_cmark_type()
+                .unwrap(),
+            NodeType::CMarkNodeEmph
+        );
+    }
+
+    #[test]
+    fn test_governing_heading() {
+        let body = "Preface\n\n# Title\n\n## Setup\n\nIntro\n\n- step *one*\n";
+        let root = parse_document(body);
+        let find_text = |content: &str| {
+            root.iter()
+                .map(|(node, _)| node)
+                .find(|node| match *node {
+                    Node::Text(ref text) => text.get_content().unwrap() == content,
+                    _ => false,
+                })
+                .expect("Document should contain the text")
+        };
+
+        for content in vec!["Intro", "one"] {
+            let heading = find_text(content)
+                .governing_heading()
+                .unwrap()
+                .expect("Text should be governed by a heading");
+            assert_eq!(heading.to_plain_text().unwrap(), "Setup\n");
+        }
+        assert!(find_text("Preface").governing_heading().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_enforce_single_h1() {
+        let root = parse_document("# One\n\n# Two\n\n## Sub\n\n# Three");
+
+        match root {
+            Node::Document(ref document) => {
+                assert_eq!(document.enforce_single_h1().unwrap(), 2);
+            }
+            _ => panic!("Did not get a Document Node after parsing."),
+        }
+
+        let levels: Vec<usize> = root.child_nodes()
+            .unwrap()
+            .iter()
+            .map(|child| match *child {
+                Node::Heading(ref heading) => heading.get_level(),
+                _ => 0,
+            })
+            .collect();
+        assert_eq!(levels, vec![1, 2, 2, 2]);
+    }
+
+    #[test]
+    fn test_image_urls() {
+        let body = "![a](a.png) [link](page.html) ![b](b.png)\n\n![again](a.png)";
+        let root = parse_document(body);
+
+        assert_eq!(
+            root.image_urls().unwrap(),
+            vec![
+                String::from("a.png"),
+                String::from("b.png"),
+                String::from("a.png"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_trim_empty_blocks() {
+        let mut root = Node::from_type(NodeType::CMarkNodeDocument).unwrap();
+        for content in vec!["", " ", "keep", "", "also keep", ""] {
+            let mut paragraph = Node::from_type(NodeType::CMarkNodeParagraph).unwrap();
+            if !content.is_empty() {
+                let mut text = Node::from_type(NodeType::CMarkNodeText).unwrap();
+                if let Node::Text(ref mut text) = text {
+                    text.set_content(&content.to_string()).unwrap();
+                }
+                paragraph.append_child(&mut text).unwrap();
+            }
+            root.append_child(&mut paragraph).unwrap();
+        }
+
+        assert_eq!(root.trim_empty_blocks().unwrap(), 3);
+
+        let contents: Vec<String> = root.child_nodes()
+            .unwrap()
+            .iter()
+            .map(|child| child.to_plain_text().unwrap())
+            .collect();
+        assert_eq!(contents, vec!["keep\n", "\n", "also keep\n"]);
+    }
+
+    #[test]
+    fn test_fence_attributes_simple() {
+        let root = parse_document("```rust\nfn main() {}\n```");
+
+        match root.first_child().unwrap() {
+            Some(Node::CodeBlock(ref block)) => {
+                let attributes = block.fence_attributes().unwrap();
+                assert_eq!(attributes.language, Some(String::from("rust")));
+                assert_eq!(attributes.id, None);
+                assert!(attributes.classes.is_empty());
+                assert!(attributes.kvs.is_empty());
+            }
+            _ => panic!("Root should have a Code Block"),
+        }
+    }
+
+    #[test]
+    fn test_fence_attributes_pandoc() {
+        let root = parse_document("```{.rust #example .highlight startFrom=\"10\"}\ncode\n```");
+
+        match root.first_child().unwrap() {
+            Some(Node::CodeBlock(ref block)) => {
+                let attributes = block.fence_attributes().unwrap();
+                assert_eq!(attributes.language, Some(String::from("rust")));
+                assert_eq!(attributes.id, Some(String::from("example")));
+                assert_eq!(attributes.classes, vec!["rust", "highlight"]);
+                assert_eq!(
+                    attributes.kvs.get("startFrom"),
+                    Some(&String::from("10"))
+                );
+            }
+            _ => panic!("Root should have a Code Block"),
+        }
+    }
+
+    #[test]
+    fn test_formatted_runs() {
+        let root = parse_document("*a **b** c*");
+        let run = |text: &str, strong: bool| FormattedRun {
+            text: String::from(text),
+            emphasis: true,
+            strong,
+            code: false,
+            link_url: None,
+        };
+
+        assert_eq!(
+            root.formatted_runs().unwrap(),
+            vec![run("a ", false), run("b", true), run(" c", false)]
+        );
+    }
+
+    #[test]
+    fn test_transform_type() {
+        let root = parse_document("Use `foo` and `bar` here");
+
+        let count = root.transform_type(NodeType::CMarkNodeCode, |node: &Node| {
+            let content = match *node {
+                Node::Code(ref code) => code.get_content()?,
+                _ => String::new(),
+            };
+            let mut strong = Node::from_type(NodeType::CMarkNodeStrong)?;
+            let mut text = Node::from_type(NodeType::CMarkNodeText)?;
+            if let Node::Text(ref mut text) = text {
+                text.set_content(&content)?;
+            }
+            strong.append_child(&mut text)?;
+            Ok(strong)
+        }).unwrap();
+
+        assert_eq!(count, 2);
+        assert_eq!(root.render_commonmark().trim(), "Use **foo** and **bar** here");
+    }
+
+    #[test]
+    fn test_render_commonmark_preserving_markers() {
+        let root =
+            parse_document_preserving_markers("+ a\n+ b\n\n> * quoted\n", &ParseOptions::default())
+                .unwrap();
+
+        let rendered = root.render_commonmark_preserving_markers().unwrap();
+
+        assert!(rendered.contains("+ a\n+ b"));
+        assert!(rendered.contains("> * quoted"));
+        assert!(!rendered.contains("- "));
+    }
+
+    #[test]
+    fn test_render_commonmark_preserving_markers_skips_code() {
+        let root =
+            parse_document_preserving_markers("    - code\n\n+ a\n", &ParseOptions::default())
+                .unwrap();
+
+        let rendered = root.render_commonmark_preserving_markers().unwrap();
+
+        assert!(rendered.contains("    - code\n"));
+        assert!(rendered.contains("+ a\n"));
+    }
+
+    #[test]
+    fn test_render_commonmark_preserving_markers_from_child() {
+        let root = parse_document_preserving_markers("* a\n* b\n", &ParseOptions::default())
+            .unwrap();
+        let list = root.first_child().unwrap().unwrap();
+
+        let rendered = list.render_commonmark_preserving_markers().unwrap();
+
+        assert!(rendered.contains("* a\n* b"));
+    }
+
+    #[test]
+    fn test_list_markers_only_recorded_on_request() {
+        let root = parse_document("* a\n* b\n");
+
+        let rendered = root.render_commonmark_preserving_markers().unwrap();
+
+        assert!(rendered.contains("- a\n- b"));
+        assert!(LIST_MARKERS.with(|markers| markers.borrow().is_empty()));
+    }
+
+    #[test]
+    fn test_list_markers_forgotten_when_freed() {
+        let root = parse_document_preserving_markers("* a\n\n+ b\n", &ParseOptions::default())
+            .unwrap();
+        let mut list = root.first_child().unwrap().unwrap();
+        assert_eq!(LIST_MARKERS.with(|markers| markers.borrow().len()), 2);
+
+        list.unlink();
+        drop(list);
+        assert_eq!(LIST_MARKERS.with(|markers| markers.borrow().len()), 1);
+
+        drop(root);
+        assert!(LIST_MARKERS.with(|markers| markers.borrow().is_empty()));
+    }
+
+    #[test]
+    fn test_sentence_count() {
+        let root = parse_document(
+            "# Intro\n\nDr. Smith paid 3.50 for it. It was cheap, e.g. next to others! Was it \
+             worth it?\n\n```\nOne. Two.\n```\n",
+        );
+
+        assert_eq!(root.sentence_count().unwrap(), 4);
+    }
+
+    #[test]
+    fn test_insert_heading_anchors() {
+        let root = parse_document("# Intro\n\n## Intro\n\n## Usage\n");
+
+        let inserted = match root {
+            Node::Document(ref document) => document.insert_heading_anchors().unwrap(),
+            _ => panic!("Expected a Document"),
+        };
+        let rendered = root.render_commonmark();
+
+        assert_eq!(inserted, 3);
+        assert!(rendered.contains("# <a id=\"intro\"></a>Intro"));
+        assert!(rendered.contains("## <a id=\"intro-1\"></a>Intro"));
+        assert!(rendered.contains("## <a id=\"usage\"></a>Usage"));
+    }
+
+    #[test]
+    fn test_lsp_range() {
+        let source = "Intro\n\nHi \u{1F600} there\n";
+        let root = parse_document(source);
+        let paragraph = root.last_child().unwrap().unwrap();
+
+        let range = paragraph.lsp_range(source).unwrap().unwrap();
+
+        assert_eq!(range.start, LspPosition { line: 2, character: 0 });
+        assert_eq!(range.end, LspPosition { line: 2, character: 11 });
+    }
+
+    #[test]
+    fn test_duplicate_adjacent_links() {
+        let root = parse_document(
+            "[docs](http://a.com) [docs](http://a.com) [docs](http://b.com) [home](http://b.com)\n",
+        );
+
+        let paragraph = root.first_child().unwrap().unwrap();
+        let second_link = paragraph.child_nodes().unwrap().remove(2);
+
+        let duplicates = root.duplicate_adjacent_links().unwrap();
+
+        assert_eq!(duplicates, vec![second_link]);
+    }
+
+    #[test]
+    fn test_render_filtered() {
+        let root = parse_document("# One\n\nFirst text.\n\n## Two\n\nSecond text.\n");
+
+        let rendered = root.render_filtered(&[NodeType::CMarkNodeHeading]).unwrap();
+
+        assert!(rendered.contains("# One"));
+        assert!(rendered.contains("## Two"));
+        assert!(!rendered.contains("text."));
+        assert!(root.render_commonmark().contains("First text."));
+    }
+
+    #[test]
+    fn test_paragraph_text_directions() {
+        let root = parse_document(
+            "\u{645}\u{631}\u{62D}\u{628}\u{627} \u{628}\u{643}\u{645}\n\nHello there\n",
+        );
+
+        let directions: Vec<TextDirection> = root.paragraph_text_directions()
+            .unwrap()
+            .into_iter()
+            .map(|(_, direction)| direction)
+            .collect();
+
+        assert_eq!(
+            directions,
+            vec![TextDirection::RightToLeft, TextDirection::LeftToRight]
+        );
+    }
+
+    #[test]
+    fn test_cap_heading_depth() {
+        let root = parse_document("# Title\n\n## Section\n\n#### Detail *here*\n");
+
+        let converted = match root {
+            Node::Document(ref document) => document.cap_heading_depth(2).unwrap(),
+            _ => panic!("Expected a Document"),
+        };
+        let rendered = root.render_commonmark();
+
+        assert_eq!(converted, 1);
+        assert!(rendered.contains("## Section"));
+        assert!(rendered.contains("**Detail here**"));
+        assert!(!rendered.contains("####"));
+    }
+
+    #[test]
+    fn test_item_complexity() {
+        let root = parse_document("- simple\n- first\n\n  second\n- nested\n  - inner\n- plain\n");
+
+        let complexity = match root.first_child().unwrap().unwrap() {
+            Node::List(ref list) => list.item_complexity().unwrap(),
+            _ => panic!("Expected a List"),
+        };
+
+        assert_eq!(complexity, vec![false, true, true, false]);
+    }
+
+    #[test]
+    fn test_merge_with_next_paragraph() {
+        let root = parse_document("First *part*.\n\nSecond part.\n\n# Heading\n");
+        let first = root.first_child().unwrap().unwrap();
+
+        assert!(first.merge_with_next_paragraph(" ").unwrap());
+        assert!(!first.merge_with_next_paragraph(" ").unwrap());
+        let heading = first.next_sibling().unwrap().unwrap();
+        assert!(!heading.merge_with_next_paragraph(" ").unwrap());
+        assert_eq!(
+            root.render_commonmark(),
+            "First *part*. Second part.\n\n# Heading\n"
+        );
+    }
+
+    #[test]
+    fn test_render_commonmark_backslash_hardbreaks() {
+        let root = parse_document("first  \nsecond\n\n```text\ncode  \n```\n");
+
+        let rendered = root.render_commonmark_backslash_hardbreaks().unwrap();
+
+        assert!(rendered.contains("first\\\nsecond"));
+        assert!(rendered.contains("code  \n"));
+    }
+
+    #[test]
+    fn test_render_commonmark_backslash_hardbreaks_skips_literal_blocks() {
+        let root = parse_document("a  \nb\n\n```\nfoo  \nbar\n```\n\n<div>  \nhtml  \n</div>\n");
+
+        let rendered = root.render_commonmark_backslash_hardbreaks().unwrap();
+
+        assert!(rendered.contains("a\\\nb"));
+        assert!(rendered.contains("foo  \n"));
+        assert!(rendered.contains("<div>  \nhtml  \n"));
+        assert!(!rendered.contains("foo\\"));
+    }
+
+    #[test]
+    fn test_max_list_nesting() {
+        let nested = parse_document("- one\n  - two\n    1. three\n- back\n");
+        let flat = parse_document("- one\n- two\n");
+
+        assert_eq!(nested.max_list_nesting().unwrap(), 3);
+        assert_eq!(flat.max_list_nesting().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_parse_with_front_matter() {
+        let source = "---\ntitle: Doc\ntags: [a]\n---\n# Heading\n\n---\n\nText\n";
+
+        let (front_matter, root) =
+            parse_with_front_matter(source, &ParseOptions::default()).unwrap();
+
+        assert_eq!(front_matter, Some("title: Doc\ntags: [a]\n".to_string()));
+        assert_eq!(root.render_commonmark(), "# Heading\n\n-----\n\nText\n");
+    }
+
+    #[test]
+    fn test_parse_without_front_matter() {
+        let source = "# Heading\n\n---\n\nText\n";
+
+        let (front_matter, root) =
+            parse_with_front_matter(source, &ParseOptions::default()).unwrap();
+
+        assert_eq!(front_matter, None);
+        assert_eq!(root.render_commonmark(), parse_document(source).render_commonmark());
+    }
+
+    #[test]
+    fn test_parse_with_leading_thematic_break() {
+        let source = "---\n\nText\n\n---\n";
+
+        let (front_matter, root) =
+            parse_with_front_matter(source, &ParseOptions::default()).unwrap();
+
+        assert_eq!(front_matter, None);
+        assert!(root.render_commonmark().contains("Text"));
+    }
+
+    #[test]
+    fn test_code_blocks_missing_language() {
+        let source = "```rust\nlet a = 1;\n```\n\n```\nplain\n```\n\n    indented\n";
+        let root = parse_document(source);
+        let unlabeled = root.child_nodes().unwrap().remove(1);
+
+        let missing = root.code_blocks_missing_language(source).unwrap();
+
+        assert_eq!(missing, vec![unlabeled]);
+    }
+
+    #[test]
+    fn test_is_fenced_indented_before_paragraph() {
+        let source = "    code\n\nText\n\n    ```\n\n~~~\n~~~\n";
+        let root = parse_document(source);
+        let blocks = root.child_nodes().unwrap();
+
+        let fenced: Vec<bool> = blocks
+            .iter()
+            .filter_map(|node| match *node {
+                Node::CodeBlock(ref block) => Some(block.is_fenced(source).unwrap()),
+                _ => None,
+            })
+            .collect();
+
+        assert_eq!(fenced, vec![false, false, true]);
+        assert!(root.code_blocks_missing_language(source).unwrap().len() == 1);
+    }
+
+    #[test]
+    fn test_content_with_line_numbers() {
+        let root = parse_document("```\nfirst\nsecond\nthird\n```\n");
+
+        let numbered = match root.first_child().unwrap().unwrap() {
+            Node::CodeBlock(ref block) => block.content_with_line_numbers(9).unwrap(),
+            _ => panic!("Expected a CodeBlock"),
+        };
+
+        assert_eq!(numbered, " 9 first\n10 second\n11 third\n");
+    }
+
+    #[test]
+    fn test_is_ancestor_of() {
+        let root = parse_document("- first\n- second\n");
+        let list = root.first_child().unwrap().unwrap();
+        let first = list.first_child().unwrap().unwrap();
+        let second = first.next_sibling().unwrap().unwrap();
+        let text = first.first_child().unwrap().unwrap().first_child().unwrap().unwrap();
+
+        assert!(list.is_ancestor_of(&text).unwrap());
+        assert!(!text.is_ancestor_of(&list).unwrap());
+        assert!(!first.is_ancestor_of(&second).unwrap());
+        assert!(!second.is_ancestor_of(&first).unwrap());
+        assert!(!list.is_ancestor_of(&list).unwrap());
+    }
+
+    #[test]
+    fn test_block_quote_attribution() {
+        let attributed = parse_document("> To be, or not to be.\n> \u{2014} Hamlet\n");
+        let unattributed = parse_document("> To be, or not to be.\n");
+
+        let attribution = |root: &Node| match root.first_child().unwrap().unwrap() {
+            Node::BlockQuote(ref quote) => quote.attribution().unwrap(),
+            _ => panic!("Expected a BlockQuote"),
+        };
+
+        assert_eq!(attribution(&attributed), Some("Hamlet".to_string()));
+        assert_eq!(attribution(&unattributed), None);
+    }
+
+    #[test]
+    fn test_collapse_single_item_lists() {
+        let root = parse_document("- - first\n  - second *item*\n");
+
+        let collapsed = root.collapse_single_item_lists().unwrap();
+
+        assert_eq!(collapsed, 1);
+        assert_eq!(root.max_list_nesting().unwrap(), 1);
+        let rendered = root.render_commonmark();
+        assert!(rendered.contains("- first\n"));
+        assert!(rendered.contains("- second *item*\n"));
+    }
+
+    #[test]
+    fn test_heading_set_level() {
+        let root = parse_document("# foo\n");
+        let mut heading = root.first_child().unwrap().unwrap();
+
+        if let Node::Heading(ref mut heading) = heading {
+            assert_eq!(heading.set_level(3).unwrap(), 1);
+            match heading.set_level(7) {
+                Err(DoogieError::InvalidHeadingLevel(7)) => (),
+                _ => panic!("Expected an InvalidHeadingLevel error"),
+            }
+        } else {
+            panic!("Expected a Heading");
+        }
+
+        assert_eq!(root.render_commonmark(), "### foo\n");
+    }
+
+    #[test]
+    fn test_extract_plain_text() {
+        let source = "# Title\n\nSome *emphasis* and `code`.\nsoft  \nhard <b>html</b>\n\n\
+                      ```\nblock\n```\n\n```\n```\n";
+
+        let text = extract_plain_text(source, &ParseOptions::default()).unwrap();
+
+        assert_eq!(text, parse_document(source).to_plain_text().unwrap());
+    }
+
+    #[test]
+    fn test_code_spans_looking_like_urls() {
+        let root = parse_document("See `https://example.com/docs` and `std::vec::Vec`.\n");
+        let paragraph = root.first_child().unwrap().unwrap();
+        let url_span = paragraph.child_nodes().unwrap().remove(1);
+
+        let spans = root.code_spans_looking_like_urls().unwrap();
+
+        assert_eq!(spans, vec![url_span]);
+    }
+
+    #[test]
+    fn test_link_set_url_and_title() {
+        let root = parse_document("[docs](http://old.com \"Old\")\n");
+        let mut link = root.first_child().unwrap().unwrap().first_child().unwrap().unwrap();
+
+        if let Node::Link(ref mut link) = link {
+            link.set_url(&"http://new.com".to_string()).unwrap();
+            link.set_title(&"New".to_string()).unwrap();
+            match link.set_title(&"bad\0title".to_string()) {
+                Err(DoogieError::NulError(_)) => (),
+                _ => panic!("Expected a NulError"),
+            }
+            assert_eq!(link.get_title().unwrap(), "New");
+        } else {
+            panic!("Expected a Link");
+        }
+
+        assert_eq!(
+            root.render_commonmark(),
+            "[docs](http://new.com \"New\")\n"
+        );
+    }
+
+    #[test]
+    fn test_list_ordered_from() {
+        let list = List::ordered_from(
+            &["Install", "Configure", "Run"],
+            1,
+            DelimType::CMarkPeriodDelim,
+        ).unwrap();
+
+        if let Node::List(ref list) = list {
+            assert!(list.get_list_type().unwrap() == ListType::CMarkOrderedList);
+            assert_eq!(list.get_start(), 1);
+        } else {
+            panic!("Expected a List");
+        }
+
+        let rendered = list.render_commonmark();
+        assert!(rendered.contains("1. Install"));
+        assert!(rendered.contains("2. Configure"));
+        assert!(rendered.contains("3. Run"));
+    }
+
+    #[test]
+    fn test_render_latex_and_man() {
+        let root = parse_document("# Title\n\nSome *text*.\n");
+
+        let latex = root.render_latex(0);
+        let man = root.render_man(0);
+
+        assert!(latex.contains("\\section{Title}"));
+        assert!(latex.contains("\\emph{text}"));
+        assert!(man.contains(".SH\nTitle"));
+    }
+
+    #[test]
+    fn test_headings_matching_title() {
+        let root = parse_document("# Getting Started\n\n## Install\n\n## getting started\n");
+        let repeated = root.last_child().unwrap().unwrap();
+
+        let matching = root.headings_matching_title().unwrap();
+
+        assert_eq!(root.title().unwrap(), Some("Getting Started".to_string()));
+        assert_eq!(matching, vec![repeated]);
+    }
+
+    #[test]
+    fn test_code_to_prose_ratio() {
+        let code_heavy = parse_document("Run `make`:\n\n```\nmake all install clean\n```\n");
+        let prose_heavy = parse_document("A long paragraph of prose mentioning `x` once.\n");
+
+        let code_ratio = code_heavy.code_to_prose_ratio().unwrap();
+        let prose_ratio = prose_heavy.code_to_prose_ratio().unwrap();
+
+        assert!(code_ratio > 0.5);
+        assert!(prose_ratio < 0.1);
+        assert!(parse_document("").code_to_prose_ratio().unwrap().abs() < ::std::f64::EPSILON);
+    }
+
+    #[test]
+    fn test_render_with_options() {
+        let root = parse_document("first\nsecond\n\n<b>raw</b>\n");
+        let options = RenderOptions {
+            sourcepos: true,
+            hardbreaks: true,
+            unsafe_html: true,
+            ..RenderOptions::default()
+        };
+        let nobreaks = RenderOptions {
+            nobreaks: true,
+            ..RenderOptions::default()
+        };
+
+        let html = root.render_html_with_options(&options);
+
+        assert!(html.contains("data-sourcepos=\"1:1-2:6\""));
+        assert!(html.contains("first<br />\nsecond"));
+        assert!(html.contains("<b>raw</b>"));
+        assert!(root.render_html_with_options(&nobreaks).contains("first second"));
+        assert_eq!(
+            root.render_commonmark(),
+            root.render_commonmark_with_options(&RenderOptions::default())
+        );
+    }
+
+    #[test]
+    fn test_enclosing_list_and_item() {
+        let root = parse_document("- step *one*\n\nAfter\n");
+        let list = root.first_child().unwrap().unwrap();
+        let item = list.first_child().unwrap().unwrap();
+        let emph = item.first_child().unwrap().unwrap().last_child().unwrap().unwrap();
+        let text = emph.first_child().unwrap().unwrap();
+        let after = root.last_child().unwrap().unwrap().first_child().unwrap().unwrap();
+
+        assert_eq!(text.enclosing_item().unwrap(), Some(item));
+        assert_eq!(text.enclosing_list().unwrap(), Some(list));
+        assert_eq!(after.enclosing_item().unwrap(), None);
+        assert_eq!(after.enclosing_list().unwrap(), None);
+    }
+
+    #[test]
+    fn test_parse_document_with_smart_punctuation() {
+        let options = ParseOptions {
+            smart: true,
+            ..ParseOptions::default()
+        };
+
+        let smart = parse_document_with_options("\"foo\" -- bar\n", &options);
+        let plain = parse_document("\"foo\" -- bar\n");
+
+        assert_eq!(smart.render_html(), "<p>\u{201C}foo\u{201D} \u{2013} bar</p>\n");
+        assert_eq!(plain.render_html(), "<p>&quot;foo&quot; -- bar</p>\n");
+    }
+
+    #[test]
+    fn test_insert_before_and_after() {
+        let root = parse_document("Middle\n");
+        let mut middle = root.first_child().unwrap().unwrap();
+        let mut before = Node::from_type(NodeType::CMarkNodeThematicBreak).unwrap();
+        let mut after = Node::from_type(NodeType::CMarkNodeThematicBreak).unwrap();
+
+        middle.insert_before(&mut before).unwrap();
+        middle.insert_after(&mut after).unwrap();
+
+        assert!(!before.manager().is_tracking(&before.pointer()));
+        assert!(!after.manager().is_tracking(&after.pointer()));
+        assert_eq!(middle.prev_sibling().unwrap(), Some(before));
+        assert_eq!(middle.next_sibling().unwrap(), Some(after));
+        assert_eq!(root.render_commonmark(), "-----\n\nMiddle\n\n-----\n");
+    }
+
+    #[test]
+    fn test_insert_after_invalid_sibling() {
+        let root = parse_document("Text\n");
+        let mut text = root.first_child().unwrap().unwrap().first_child().unwrap().unwrap();
+        let mut paragraph = Node::from_type(NodeType::CMarkNodeParagraph).unwrap();
+
+        assert!(text.insert_after(&mut paragraph).is_err());
+        assert!(paragraph.manager().is_tracking(&paragraph.pointer()));
+    }
+
+    #[test]
+    fn test_insert_without_parent_or_next_to_itself() {
+        let root = parse_document("Text\n\nMore\n");
+        let mut orphan = Node::from_type(NodeType::CMarkNodeParagraph).unwrap();
+        let mut first = root.first_child().unwrap().unwrap();
+        let mut same = first.itself().unwrap();
+
+        match orphan.insert_before(&mut first) {
+            Err(DoogieError::NoParent) => (),
+            _ => panic!("Expected a NoParent error"),
+        }
+        match first.insert_after(&mut same) {
+            Err(DoogieError::CyclicAppend) => (),
+            _ => panic!("Expected a CyclicAppend error"),
+        }
+
+        assert_eq!(first.parent().unwrap(), Some(root.itself().unwrap()));
+        assert!(orphan.first_child().unwrap().is_none());
+        assert_eq!(root.render_commonmark(), "Text\n\nMore\n");
+    }
+
+    #[test]
+    fn test_reading_time_minutes() {
+        let prose: Vec<&str> = ::std::iter::repeat("word").take(400).collect();
+        let root = parse_document(&format!("# {}\n\n{}\n", prose[0], prose[1..].join(" ")));
+        let code: Vec<&str> = ::std::iter::repeat("code").take(100).collect();
+        let mixed = parse_document(&format!(
+            "{}\n\n```\n{}\n```\n",
+            prose[..200].join(" "),
+            code.join(" ")
+        ));
+
+        assert_eq!(root.word_count().unwrap(), 400);
+        assert!((root.reading_time_minutes(200).unwrap() - 2.0).abs() < 1e-9);
+        assert!((mixed.reading_time_minutes(200).unwrap() - 1.5).abs() < 1e-9);
+        assert!((mixed.reading_time_minutes_weighted(200, 2.0).unwrap() - 2.0).abs() < 1e-9);
+        assert!(root.reading_time_minutes(0).unwrap().abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_prepend_child() {
+        let mut root = Node::from_type(NodeType::CMarkNodeDocument).unwrap();
+        for _ in 0..3 {
+            let mut paragraph = Node::from_type(NodeType::CMarkNodeParagraph).unwrap();
+            root.append_child(&mut paragraph).unwrap();
+        }
+        let mut heading = Node::from_type(NodeType::CMarkNodeHeading).unwrap();
+        let mut item = Node::from_type(NodeType::CMarkNodeItem).unwrap();
+
+        root.prepend_child(&mut heading).unwrap();
+
+        assert_eq!(root.first_child().unwrap(), Some(heading));
+        assert_eq!(root.child_nodes().unwrap().len(), 4);
+        match root.prepend_child(&mut item) {
+            Err(DoogieError::InvalidChild { .. }) => (),
+            _ => panic!("Expected an Item to be rejected as a child of a Document"),
+        }
+    }
+
+    #[test]
+    fn test_images_with_disallowed_extensions() {
+        let root = parse_document(
+            "![a](img/a.png) ![b](https://x.com/b.JPG?size=2) ![c](../c.bmp#top)\n",
+        );
+        let paragraph = root.first_child().unwrap().unwrap();
+        let bitmap = paragraph.last_child().unwrap().unwrap();
+
+        let disallowed = root.images_with_disallowed_extensions(&["png", ".jpg"]).unwrap();
+
+        assert_eq!(disallowed, vec![(bitmap, String::from("../c.bmp#top"))]);
+    }
+
+    #[test]
+    fn test_replace_with() {
+        let root = parse_document("Old *text*\n\n> New *quote*\n");
+        let mut old = root.first_child().unwrap().unwrap();
+        let mut quote = root.last_child().unwrap().unwrap();
+        let mut replacement = quote.first_child().unwrap().unwrap();
+
+        old.replace_with(&mut replacement).unwrap();
+
+        assert_eq!(root.first_child().unwrap(), Some(replacement));
+        assert_eq!(old.parent().unwrap(), None);
+        assert!(old.manager().is_tracking(&old.pointer()));
+        assert_eq!(old.to_plain_text().unwrap(), "Old text\n");
+        assert_eq!(quote.first_child().unwrap(), None);
+        quote.unlink();
+        assert_eq!(root.render_commonmark(), "New *quote*\n");
+    }
+
+    #[test]
+    fn test_replace_with_invalid_replacement() {
+        let root = parse_document("Old *text*\n\n> > New\n");
+        let other = parse_document("- item\n");
+        let mut orphan = Node::from_type(NodeType::CMarkNodeParagraph).unwrap();
+        let mut paragraph = root.first_child().unwrap().unwrap();
+        let mut outer = root.last_child().unwrap().unwrap();
+        let mut inner = outer.first_child().unwrap().unwrap().first_child().unwrap().unwrap();
+        let mut item = other.first_child().unwrap().unwrap().first_child().unwrap().unwrap();
+
+        match orphan.replace_with(&mut outer) {
+            Err(DoogieError::NoParent) => (),
+            _ => panic!("Expected a NoParent error"),
+        }
+        match paragraph.replace_with(&mut item) {
+            Err(DoogieError::InvalidChild { .. }) => (),
+            _ => panic!("Expected an InvalidChild error"),
+        }
+        match inner.replace_with(&mut outer) {
+            Err(DoogieError::CyclicAppend) => (),
+            _ => panic!("Expected a CyclicAppend error"),
+        }
+
+        assert_eq!(root.render_commonmark(), "Old *text*\n\n> > New\n");
+        assert!(other.tree_eq(&parse_document("- item\n")).unwrap());
+        assert!(orphan.first_child().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_split_text_at() {
+        let root = parse_document("hello world\n");
+        let paragraph = root.first_child().unwrap().unwrap();
+        let text = paragraph.first_child().unwrap().unwrap();
+
+        let (first, second) = text.split_text_at(5).unwrap();
+
+        match (first, second) {
+            (Node::Text(ref first), Node::Text(ref second)) => {
+                assert_eq!(first.get_content().unwrap(), "hello");
+                assert_eq!(second.get_content().unwrap(), " world");
+            }
+            _ => panic!("Expected two Text nodes"),
+        }
+        assert_eq!(paragraph.child_nodes().unwrap().len(), 2);
+        assert_eq!(root.render_commonmark(), "hello world\n");
+    }
+
+    #[test]
+    fn test_split_text_at_invalid() {
+        let root = parse_document("h\u{E9}llo\n");
+        let paragraph = root.first_child().unwrap().unwrap();
+        let text = paragraph.first_child().unwrap().unwrap();
+
+        match text.split_text_at(2) {
+            Err(DoogieError::InvalidOffset(2)) => (),
+            _ => panic!("Expected an InvalidOffset error"),
+        }
+        match text.split_text_at(42) {
+            Err(DoogieError::InvalidOffset(42)) => (),
+            _ => panic!("Expected an InvalidOffset error"),
+        }
+        match paragraph.split_text_at(0) {
+            Err(DoogieError::UnsupportedNodeType(NodeType::CMarkNodeParagraph)) => (),
+            _ => panic!("Expected an UnsupportedNodeType error"),
+        }
+    }
+
+    #[test]
+    fn test_longest_code_block() {
+        let root = parse_document("```\na\nb\n```\n\n    one\n    two\n    three\n\n```\nc\n```\n");
+        let indented = root.child_nodes().unwrap().remove(1);
+
+        let longest = root.longest_code_block().unwrap();
+
+        assert_eq!(longest, Some((indented, 3)));
+        assert_eq!(parse_document("Text\n").longest_code_block().unwrap(), None);
+    }
+
+    #[test]
+    fn test_parse_document_checked() {
+        let inputs = ["", "# Title\n\nText\n", "\u{0}\u{FFFD}[[[**_`<!--", "> - > 1. ```"];
+
+        for input in inputs.iter() {
+            let root = parse_document_checked(input).unwrap();
+            assert_eq!(root.get_cmark_type().unwrap(), NodeType::CMarkNodeDocument);
+            assert_eq!(root.render_commonmark(), parse_document(input).render_commonmark());
+        }
+    }
+
+    #[test]
+    fn test_block_html_fragments() {
+        let root = parse_document("# Title\n\nFirst *one*\n\nSecond\n");
+        let blocks = root.child_nodes().unwrap();
+
+        let fragments = match root {
+            Node::Document(ref document) => document.block_html_fragments().unwrap(),
+            _ => panic!("Expected a Document"),
+        };
+
+        assert_eq!(
+            fragments,
+            vec![
+                (blocks[0].itself().unwrap(), String::from("<h1>Title</h1>\n")),
+                (blocks[1].itself().unwrap(), String::from("<p>First <em>one</em></p>\n")),
+                (blocks[2].itself().unwrap(), String::from("<p>Second</p>\n")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_end_line_and_column() {
+        let root = parse_document("# Title\n\nSome text\nmore\n");
+        let heading = root.first_child().unwrap().unwrap();
+        let paragraph = root.last_child().unwrap().unwrap();
+
+        assert_eq!((heading.get_end_line(), heading.get_end_column()), (1, 7));
+        assert_eq!((paragraph.get_start_line(), paragraph.get_start_column()), (3, 1));
+        assert_eq!((paragraph.get_end_line(), paragraph.get_end_column()), (4, 4));
+    }
+
+    #[test]
+    fn test_fix_empty_link_text_use_url() {
+        let root =
+            parse_document("See [](http://a.com) and [ ](http://b.com) or [c](http://c.com).\n");
+
+        let fixed = root.fix_empty_link_text(FallbackText::UseUrl).unwrap();
+
+        assert_eq!(fixed, 2);
+        assert_eq!(
+            root.to_plain_text().unwrap(),
+            "See http://a.com and http://b.com or c.\n"
+        );
+    }
+
+    #[test]
+    fn test_fix_empty_link_text_remove() {
+        let root = parse_document("See [](http://a.com) or [![logo](l.png)](http://c.com).\n");
+
+        let fixed = root.fix_empty_link_text(FallbackText::Remove).unwrap();
+
+        assert_eq!(fixed, 1);
+        assert_eq!(
+            root.count(|node_type| node_type == NodeType::CMarkNodeLink).unwrap(),
+            1
+        );
+        assert_eq!(root.to_plain_text().unwrap(), "See  or logo.\n");
+    }
+
+    #[test]
+    fn test_list_tight() {
+        let tight = parse_document("- a\n- b\n");
+        let loose = parse_document("- a\n\n- b\n");
+        let is_tight = |root: &Node| match root.first_child().unwrap().unwrap() {
+            Node::List(ref list) => list.is_tight(),
+            _ => panic!("Expected a List"),
+        };
+
+        assert!(is_tight(&tight));
+        assert!(!is_tight(&loose));
+
+        if let Node::List(ref mut list) = loose.first_child().unwrap().unwrap() {
+            list.set_tight(true).unwrap();
+        }
+
+        assert!(is_tight(&loose));
+        assert_eq!(loose.render_commonmark(), tight.render_commonmark());
+    }
+
+    #[test]
+    fn test_adjacent_heading_pairs() {
+        let adjacent = parse_document("## Setup\n### Install\n\nText\n");
+        let separated = parse_document("## Setup\n\nText\n\n### Install\n");
+        let setup = adjacent.first_child().unwrap().unwrap();
+        let install = setup.next_sibling().unwrap().unwrap();
+
+        assert_eq!(adjacent.adjacent_heading_pairs().unwrap(), vec![(setup, install)]);
+        assert!(separated.adjacent_heading_pairs().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_list_start() {
+        let root = parse_document("3. one\n4. two\n");
+
+        if let Node::List(ref mut list) = root.first_child().unwrap().unwrap() {
+            assert_eq!(list.get_start(), 3);
+            list.set_start(10).unwrap();
+            assert_eq!(list.get_start(), 10);
+        } else {
+            panic!("Expected a List");
+        }
+
+        let rendered = root.render_commonmark();
+        assert!(rendered.contains("10. one"));
+        assert!(rendered.contains("11. two"));
+    }
+
+    #[test]
+    fn test_list_set_type_and_delim() {
+        let root = parse_document("- one\n- two\n");
+
+        if let Node::List(ref mut list) = root.first_child().unwrap().unwrap() {
+            list.set_list_type(ListType::CMarkOrderedList).unwrap();
+            list.set_delim_type(DelimType::CMarkParenDelim).unwrap();
+            list.set_start(1).unwrap();
+            assert!(list.get_list_type().unwrap() == ListType::CMarkOrderedList);
+            assert!(list.get_delim_type().unwrap() == DelimType::CMarkParenDelim);
+        } else {
+            panic!("Expected a List");
+        }
+
+        let rendered = root.render_commonmark();
+        assert!(rendered.contains("1) one"));
+        assert!(rendered.contains("2) two"));
+    }
+
+    #[test]
+    fn test_streaming_parser() {
+        let source = "# Title\n\nSome *emphasized* text\nacross lines.\n\n- one\n- two\n";
+        let mut parser = StreamingParser::new();
+
+        for chunk in source.as_bytes().chunks(5) {
+            parser.feed(chunk);
+        }
+        let root = parser.finish();
+
+        assert_eq!(root.render_xml(), parse_document(source).render_xml());
+    }
+
+    #[test]
+    fn test_children() {
+        let root = parse_document("# Title\n\n- one\n- two\n\nText\n");
+
+        let types: Vec<NodeType> = root.children()
+            .map(|child| child.get_cmark_type().unwrap())
+            .collect();
+
+        assert_eq!(
+            types,
+            vec![
+                NodeType::CMarkNodeHeading,
+                NodeType::CMarkNodeList,
+                NodeType::CMarkNodeParagraph,
+            ]
+        );
+        let title = root.first_child().unwrap().unwrap().first_child().unwrap().unwrap();
+        assert_eq!(title.children().count(), 0);
+    }
+
+    #[test]
+    fn test_display() {
+        let root = parse_document("# Title\n\nSome *text*\n");
+        let paragraph = root.last_child().unwrap().unwrap();
+
+        assert_eq!(format!("{}", root), "# Title\n\nSome *text*\n");
+        assert_eq!(paragraph.to_string(), "Some *text*\n");
+    }
+
+    #[test]
+    fn test_deep_copy() {
+        let body = "## Title\n\n3) [link](http://a.com \"Title\")\n4) ![img](b.png)\n\n\
+                    ```rust\nlet a = 1;\n```\n";
+        let root = parse_document(body);
+
+        let copy = root.deep_copy().unwrap();
+
+        assert_ne!(copy, root);
+        assert_eq!(copy.render_xml(), root.render_xml());
+        assert!(copy.manager().is_tracking(&copy.pointer()));
+
+        let mut heading = copy.first_child().unwrap().unwrap();
+        if let Node::Heading(ref mut heading) = heading {
+            heading.set_level(1).unwrap();
+        }
+        heading.last_child().unwrap().unwrap().unlink();
+
+        assert_eq!(root.render_commonmark(), parse_document(body).render_commonmark());
+        assert!(root.render_commonmark().starts_with("## Title\n"));
+        assert!(!copy.render_commonmark().contains("Title\n"));
+    }
+
+    #[test]
+    fn test_accept_visitor() {
+        struct Recorder {
+            events: Vec<String>,
+        }
+
+        impl Visitor for Recorder {
+            fn visit_heading(&mut self, node: &Node, event: &IterEventType) {
+                self.events.push(format!("{:?} heading {}", event, node.get_start_line()));
+            }
+
+            fn visit_text(&mut self, node: &Node, event: &IterEventType) {
+                if let Node::Text(ref text) = *node {
+                    self.events.push(format!("{:?} {}", event, text.get_content().unwrap()));
+                }
             }
         }
+
+        let root = parse_document("# One\n\nTwo *three*\n");
+        let mut recorder = Recorder { events: Vec::new() };
+
+        root.accept(&mut recorder);
+
+        assert_eq!(
+            recorder.events,
+            vec!["Enter heading 1", "Enter One", "Exit heading 1", "Enter Two ", "Enter three"]
+        );
+    }
+
+    #[test]
+    fn test_find_all_items() {
+        let root = parse_document("- one\n- two\n- three\n\nSome text\n");
+
+        let items = root.find_all(NodeType::CMarkNodeItem).unwrap();
+        let texts: Vec<String> = items
+            .iter()
+            .map(|item| match item.first_child().unwrap().unwrap().first_child().unwrap() {
+                Some(Node::Text(ref text)) => text.get_content().unwrap(),
+                _ => panic!("Expected a text node"),
+            })
+            .collect();
+
+        assert_eq!(texts, vec!["one", "two", "three"]);
+        assert!(root.find_all(NodeType::CMarkNodeImage).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_node_type_display() {
+        let expected = vec![
+            (NodeType::CMarkNodeNone, "none"),
+            (NodeType::CMarkNodeDocument, "document"),
+            (NodeType::CMarkNodeBlockQuote, "block_quote"),
+            (NodeType::CMarkNodeList, "list"),
+            (NodeType::CMarkNodeItem, "item"),
+            (NodeType::CMarkNodeCodeBlock, "code_block"),
+            (NodeType::CMarkNodeHtmlBlock, "html_block"),
+            (NodeType::CMarkNodeCustomBlock, "custom_block"),
+            (NodeType::CMarkNodeParagraph, "paragraph"),
+            (NodeType::CMarkNodeHeading, "heading"),
+            (NodeType::CMarkNodeThematicBreak, "thematic_break"),
+            (NodeType::CMarkNodeText, "text"),
+            (NodeType::CMarkNodeSoftbreak, "softbreak"),
+            (NodeType::CMarkNodeLinebreak, "linebreak"),
+            (NodeType::CMarkNodeCode, "code"),
+            (NodeType::CMarkNodeHtmlInline, "html_inline"),
+            (NodeType::CMarkNodeCustomInline, "custom_inline"),
+            (NodeType::CMarkNodeEmph, "emph"),
+            (NodeType::CMarkNodeStrong, "strong"),
+            (NodeType::CMarkNodeLink, "link"),
+            (NodeType::CMarkNodeImage, "image"),
+        ];
+
+        for (node_type, name) in expected {
+            assert_eq!(node_type.to_string(), name);
+        }
     }
 
     #[test]
-    fn test_image_children() {
-        for i in 1..21 {
-            let node_type = NodeType::CMarkNodeImage;
-            let other_type = NodeType::try_from(i).unwrap();
-            let mut node = Node::from_type(node_type).unwrap();
-            let mut child = Node::from_type(other_type.clone()).unwrap();
-            match node.can_append_child(&child).unwrap() {
-                true => assert!(
-                    IMAGE_CHILDREN.contains(&other_type),
-                    "{:?} should not have been a valid block quote child, but was",
-                    other_type
-                ),
-                false => assert!(
-                    !IMAGE_CHILDREN.contains(&other_type),
-                    "{:?} should be a valid block quote child, but was not",
-                    other_type
-                ),
+    fn test_node_type_from_str_round_trip() {
+        for i in 0..21u32 {
+            let node_type = NodeType::try_from(i).unwrap();
+            let name = node_type.to_string();
+
+            assert_eq!(NodeType::try_from(name.as_str()).unwrap(), node_type);
+        }
+
+        match NodeType::try_from("footnote") {
+            Err(DoogieError::UnknownNodeType(ref name)) => assert_eq!(name, "footnote"),
+            _ => panic!("Expected an UnknownNodeType error"),
+        }
+    }
+
+    #[test]
+    fn test_unset_attributes_are_empty() {
+        match Node::from_type(NodeType::CMarkNodeLink).unwrap() {
+            Node::Link(ref link) => {
+                assert_eq!(link.get_url().unwrap(), "");
+                assert_eq!(link.get_title().unwrap(), "");
             }
-            match node.append_child(&mut child) {
-                Err(_) => assert!(
-                    !IMAGE_CHILDREN.contains(&other_type),
-                    "{:?} should be able to append, but was not",
-                    other_type
-                ),
-                Ok(_) => assert!(
-                    IMAGE_CHILDREN.contains(&other_type),
-                    "{:?} should not have been able to append, but was",
-                    other_type
-                ),
+            _ => panic!("Expected a link node"),
+        }
+
+        match Node::from_type(NodeType::CMarkNodeImage).unwrap() {
+            Node::Image(ref image) => {
+                assert_eq!(image.get_url().unwrap(), "");
+                assert_eq!(image.get_title().unwrap(), "");
+            }
+            _ => panic!("Expected an image node"),
+        }
+
+        match Node::from_type(NodeType::CMarkNodeCodeBlock).unwrap() {
+            Node::CodeBlock(ref block) => assert_eq!(block.get_fence_info().unwrap(), ""),
+            _ => panic!("Expected a code block node"),
+        }
+    }
+
+    #[test]
+    fn test_sibling_iterators() {
+        let root = parse_document("# One\n\nTwo\n\n## Three\n\nFour\n");
+        let second = root.first_child().unwrap().unwrap().next_sibling().unwrap().unwrap();
+
+        let following: Vec<Node> = second.following_siblings().map(|n| n.unwrap()).collect();
+        let preceding: Vec<Node> = second.preceding_siblings().map(|n| n.unwrap()).collect();
+
+        assert_eq!(following.len(), 2);
+        assert_eq!(following[0].get_start_line(), 5);
+        assert_eq!(following[1].get_start_line(), 7);
+        assert_eq!(preceding.len(), 1);
+        assert_eq!(preceding[0], root.first_child().unwrap().unwrap());
+        assert_eq!(root.following_siblings().count(), 0);
+    }
+
+    #[test]
+    fn test_ancestors_innermost_first() {
+        let root = parse_document("> - *deep*\n");
+        let text = root
+            .iter()
+            .map(|(node, _)| node)
+            .find(|node| node.get_cmark_type().unwrap() == NodeType::CMarkNodeText)
+            .unwrap();
+
+        let chain: Vec<NodeType> = text
+            .ancestors()
+            .map(|node| node.unwrap().get_cmark_type().unwrap())
+            .collect();
+
+        assert_eq!(
+            chain,
+            vec![
+                NodeType::CMarkNodeEmph,
+                NodeType::CMarkNodeParagraph,
+                NodeType::CMarkNodeItem,
+                NodeType::CMarkNodeList,
+                NodeType::CMarkNodeBlockQuote,
+                NodeType::CMarkNodeDocument,
+            ]
+        );
+        assert_eq!(root.ancestors().count(), 0);
+    }
+
+    #[test]
+    fn test_depth() {
+        let root = parse_document("# Title\n\n> quoted *text*\n");
+        let depths: Vec<(NodeType, usize)> = root
+            .iter()
+            .filter(|&(_, ref event)| *event == IterEventType::Enter)
+            .map(|(node, _)| (node.get_cmark_type().unwrap(), node.depth().unwrap()))
+            .collect();
+
+        assert_eq!(
+            depths,
+            vec![
+                (NodeType::CMarkNodeDocument, 0),
+                (NodeType::CMarkNodeHeading, 1),
+                (NodeType::CMarkNodeText, 2),
+                (NodeType::CMarkNodeBlockQuote, 1),
+                (NodeType::CMarkNodeParagraph, 2),
+                (NodeType::CMarkNodeText, 3),
+                (NodeType::CMarkNodeEmph, 3),
+                (NodeType::CMarkNodeText, 4),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_count_descendants() {
+        let root = parse_document("# Title\n\nSome *emphasis*\nand a [link](url)\n");
+
+        fn walk(node: &Node) -> usize {
+            node.children().map(|child| 1 + walk(&child)).sum()
+        }
+
+        // heading, text, paragraph, text, emph, text, softbreak, text, link, text
+        assert_eq!(root.count_descendants(), 10);
+        assert_eq!(root.count_descendants(), walk(&root));
+
+        let heading = root.first_child().unwrap().unwrap();
+        assert_eq!(heading.count_descendants(), 1);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serialize_heading() {
+        let root = parse_document("# Hi");
+
+        let json = serde_json::to_value(&root).unwrap();
+        let heading = &json["children"][0];
+        let text = &heading["children"][0];
+
+        assert_eq!(json["type"], "document");
+        assert_eq!(json["children"].as_array().unwrap().len(), 1);
+        assert_eq!(heading["type"], "heading");
+        assert_eq!(heading["level"], 1);
+        assert_eq!(heading["start_line"], 1);
+        assert_eq!(heading["start_column"], 1);
+        assert_eq!(heading["end_column"], 4);
+        assert!(heading.get("literal").is_none());
+        assert_eq!(text["type"], "text");
+        assert_eq!(text["literal"], "Hi");
+        assert!(text["children"].as_array().unwrap().is_empty());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_deserialize_round_trip() {
+        let source = "# Title\n\nSome *emphasis* and `code`\nover two lines\n\n\
+                      3. three\n4. four\n\n```rust\nlet x = 1;\n```\n\n\
+                      > [link](http://example.com \"Title\") ![alt](image.png)\n";
+        let root = parse_document(source);
+
+        let json = serde_json::to_string(&root).unwrap();
+        let rebuilt: Node = serde_json::from_str(&json).unwrap();
+
+        assert_ne!(rebuilt, root);
+        assert_eq!(rebuilt.render_commonmark(), root.render_commonmark());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_deserialize_unknown_list_type() {
+        let json = r#"{"type": "document", "children": [
+            {"type": "list", "list_type": "numbered", "children": [{"type": "item"}]}
+        ]}"#;
+
+        let serialized: SerializedNode = serde_json::from_str(json).unwrap();
+        match serialized.to_node() {
+            Err(DoogieError::UnknownListAttribute(ref name)) => assert_eq!(name, "numbered"),
+            _ => panic!("Expected an UnknownListAttribute error"),
+        }
+        assert!(serde_json::from_str::<Node>(json).is_err());
+    }
+
+    #[test]
+    fn test_parse_reader() {
+        let mut reader = Cursor::new(b"# Title\n\nSome text\n".to_vec());
+
+        let root = parse_reader(&mut reader).unwrap();
+
+        assert_eq!(root.render_commonmark(), "# Title\n\nSome text\n");
+        assert_eq!(reader.position(), 20);
+    }
+
+    #[test]
+    fn test_parse_file() {
+        let path = env::temp_dir().join("doogie_test_parse_file.md");
+        let empty_path = env::temp_dir().join("doogie_test_parse_file_empty.md");
+        fs::write(&path, "# Title\n\nSome text\n").unwrap();
+        fs::write(&empty_path, "").unwrap();
+
+        let root = parse_file(&path).unwrap();
+        let empty = parse_file(&empty_path).unwrap();
+        let missing = parse_file(env::temp_dir().join("doogie_test_parse_file_missing.md"));
+        fs::remove_file(&path).unwrap();
+        fs::remove_file(&empty_path).unwrap();
+
+        assert_eq!(root.render_commonmark(), "# Title\n\nSome text\n");
+        match empty {
+            Node::Document(_) => assert!(empty.first_child().unwrap().is_none()),
+            _ => panic!("Expected a document node"),
+        }
+        match missing {
+            Err(DoogieError::IOError(_)) => (),
+            _ => panic!("Expected an IOError"),
+        }
+    }
+
+    #[test]
+    fn test_write_rendered_output() {
+        struct FailingWriter;
+
+        impl Write for FailingWriter {
+            fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+                Err(io::Error::new(io::ErrorKind::BrokenPipe, "closed"))
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let root = parse_document("# Title\n\nSome *text*\n");
+        let mut commonmark = Vec::new();
+        let mut html = Vec::new();
+
+        root.write_commonmark(&mut commonmark).unwrap();
+        root.write_html(&mut html).unwrap();
+
+        assert_eq!(String::from_utf8(commonmark).unwrap(), root.render_commonmark());
+        assert_eq!(String::from_utf8(html).unwrap(), root.render_html());
+        match root.write_html(&mut FailingWriter) {
+            Err(DoogieError::IOError(_)) => (),
+            _ => panic!("Expected an IOError"),
+        }
+    }
+
+    #[test]
+    fn test_write_rendered_output_with_options() {
+        let root = parse_document("first\nsecond\n");
+        let options = RenderOptions {
+            sourcepos: true,
+            hardbreaks: true,
+            ..RenderOptions::default()
+        };
+        let mut commonmark = Vec::new();
+        let mut html = Vec::new();
+
+        root.write_commonmark_with_options(&mut commonmark, &options).unwrap();
+        root.write_html_with_options(&mut html, &options).unwrap();
+
+        assert_eq!(
+            String::from_utf8(commonmark).unwrap(),
+            root.render_commonmark_with_options(&options)
+        );
+        assert_eq!(
+            String::from_utf8(html).unwrap(),
+            root.render_html_with_options(&options)
+        );
+        assert_ne!(root.render_html_with_options(&options), root.render_html());
+    }
+
+    #[test]
+    fn test_document_builder() {
+        let root = DocumentBuilder::new()
+            .heading(2, "Title")
+            .paragraph("Some text")
+            .bullet_list(vec!["one", "two"])
+            .code_block("rust", "let x = 1;\n")
+            .build()
+            .unwrap();
+
+        let rendered = root.render_commonmark();
+        assert!(rendered.starts_with("## Title\n\nSome text\n\n"));
+        assert!(rendered.contains("- one\n"));
+        assert!(rendered.contains("- two\n"));
+        assert!(rendered.contains("let x = 1;\n```\n"));
+
+        let reparsed = parse_document(&rendered);
+        assert_eq!(reparsed.render_commonmark(), rendered);
+        match reparsed.last_child().unwrap() {
+            Some(Node::CodeBlock(ref block)) => {
+                assert_eq!(block.get_fence_info().unwrap(), "rust");
+                assert_eq!(block.get_content().unwrap(), "let x = 1;\n");
+            }
+            _ => panic!("Expected a code block node"),
+        }
+    }
+
+    #[test]
+    fn test_invalid_child_error() {
+        let root = parse_document("Some text\n");
+        let mut paragraph = root.first_child().unwrap().unwrap();
+        let mut text = paragraph.first_child().unwrap().unwrap();
+        let mut item = Node::from_type(NodeType::CMarkNodeItem).unwrap();
+
+        match paragraph.append_child(&mut item) {
+            Err(DoogieError::InvalidChild { parent, child }) => {
+                assert_eq!(parent, NodeType::CMarkNodeParagraph);
+                assert_eq!(child, NodeType::CMarkNodeItem);
+            }
+            _ => panic!("Expected an InvalidChild error"),
+        }
+        match text.insert_before(&mut item) {
+            Err(DoogieError::InvalidChild { parent, child }) => {
+                assert_eq!(parent, NodeType::CMarkNodeParagraph);
+                assert_eq!(child, NodeType::CMarkNodeItem);
+            }
+            _ => panic!("Expected an InvalidChild error"),
+        }
+        assert_eq!(root.render_commonmark(), "Some text\n");
+    }
+
+    #[test]
+    fn test_normalize_equivalent_documents() {
+        let groups = vec![
+            vec![
+                "Title\n=====\n\nSome *emphasis* and **strong** text\n",
+                "# Title #\n\n\nSome _emphasis_ and __strong__ text   \n",
+                "   #   Title\n   Some *emphasis* and **strong** text\n",
+            ],
+            vec!["* one\n* two\n", "+ one\n+ two\n\n\n", "- one\n-   two\n"],
+            vec!["A \\*literal\\* star\n", "A \\*literal* star\n"],
+        ];
+
+        let mut normalized_groups = Vec::new();
+        for group in groups {
+            let normalized: Vec<String> = group
+                .iter()
+                .map(|source| parse_document(source).normalize().unwrap())
+                .collect();
+
+            for other in &normalized[1..] {
+                assert_eq!(other, &normalized[0]);
             }
+            assert_eq!(parse_document(&normalized[0]).normalize().unwrap(), normalized[0]);
+            normalized_groups.push(normalized[0].clone());
+        }
+
+        assert_ne!(normalized_groups[0], normalized_groups[1]);
+        assert_ne!(normalized_groups[1], normalized_groups[2]);
+    }
+
+    #[test]
+    fn test_tree_eq() {
+        let source = "# Title\n\nSome *text* with a [link](url \"title\")\n\n1. one\n2. two\n";
+        let root = parse_document(source);
+        let same = parse_document(source);
+        let other_markup = parse_document(
+            "Title\n=====\n\nSome _text_ with a [link](url 'title')\n\n1. one\n1. two\n",
+        );
+
+        assert_ne!(root, same);
+        assert!(root.tree_eq(&same).unwrap());
+        assert!(root.tree_eq(&other_markup).unwrap());
+        assert!(!root.tree_eq(&parse_document(&source.replace("# ", "## "))).unwrap());
+        assert!(!root.tree_eq(&parse_document(&source.replace("(url", "(other"))).unwrap());
+        assert!(!root.tree_eq(&parse_document(&source.replace("1. ", "1) "))).unwrap());
+        assert!(!root.tree_eq(&parse_document(&source.replace("2. two\n", ""))).unwrap());
+    }
+
+    #[test]
+    fn test_detach_keeps_descendants_valid() {
+        let root = parse_document("First\n\nSecond *part*\n");
+        let paragraph = root.last_child().unwrap().unwrap();
+        let text = paragraph.first_child().unwrap().unwrap();
+
+        let detached = paragraph.detach();
+        assert!(detached.parent().unwrap().is_none());
+        assert_eq!(root.render_commonmark(), "First\n");
+        drop(root);
+
+        match text {
+            Node::Text(ref text) => assert_eq!(text.get_content().unwrap(), "Second "),
+            _ => panic!("Expected a text node"),
+        }
+        assert_eq!(detached.render_commonmark(), "Second *part*\n");
+
+        let mut document = Node::from_type(NodeType::CMarkNodeDocument).unwrap();
+        let mut detached = detached;
+        document.append_child(&mut detached).unwrap();
+        drop(detached);
+        assert_eq!(document.render_commonmark(), "Second *part*\n");
+    }
+
+    #[test]
+    fn test_wrap_in_document() {
+        let root = parse_document("Some *emphasis* here\n\n> Quoted\n");
+        let emph = root.first_child().unwrap().unwrap().children().nth(1).unwrap();
+        let quote = root.last_child().unwrap().unwrap();
+
+        let wrapped_emph = emph.wrap_in_document().unwrap();
+        let wrapped_quote = quote.wrap_in_document().unwrap();
+
+        assert_eq!(wrapped_emph.render_commonmark(), "*emphasis*\n");
+        assert_eq!(wrapped_quote.render_commonmark(), "> Quoted\n");
+        assert_eq!(root.render_commonmark(), "Some  here\n");
+        match wrapped_emph.wrap_in_document() {
+            Err(DoogieError::UnsupportedNodeType(NodeType::CMarkNodeDocument)) => (),
+            _ => panic!("Expected an UnsupportedNodeType error"),
+        }
+    }
+
+    #[test]
+    fn test_ancestor_of_type() {
+        let root = parse_document("> # Quoted *heading*\n");
+        let emph_text = root.find_all(NodeType::CMarkNodeText).unwrap().pop().unwrap();
+
+        let heading = emph_text.ancestor_of_type(NodeType::CMarkNodeHeading).unwrap();
+        let quote = emph_text.ancestor_of_type(NodeType::CMarkNodeBlockQuote).unwrap();
+
+        assert_eq!(heading, root.first_child().unwrap().unwrap().first_child().unwrap());
+        assert_eq!(quote, root.first_child().unwrap());
+        assert!(emph_text.ancestor_of_type(NodeType::CMarkNodeList).unwrap().is_none());
+        assert!(emph_text.ancestor_of_type(NodeType::CMarkNodeText).unwrap().is_none());
+        assert!(root.ancestor_of_type(NodeType::CMarkNodeDocument).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_replace_text() {
+        let root = parse_document(
+            "# Hello NAME\n\nDear NAME, see `NAME` and [NAME](NAME)\n\n```\nNAME\n```\n",
+        );
+
+        let expected = parse_document(
+            "# Hello World\n\nDear World, see `World` and [World](NAME)\n\n```\nWorld\n```\n",
+        );
+
+        assert_eq!(root.replace_text("NAME", "World").unwrap(), 5);
+        assert!(root.tree_eq(&expected).unwrap());
+        assert_eq!(root.replace_text("NAME", "World").unwrap(), 0);
+        assert_eq!(root.replace_text("", "World").unwrap(), 0);
+    }
+
+    #[test]
+    fn test_into_iter_outlives_root_binding() {
+        fn parse_and_iterate(source: &str) -> NodeIterator {
+            let root = parse_document(source);
+            root.replace_text("old", "new").unwrap();
+            root.into_iter()
+        }
+
+        let texts: Vec<String> = parse_and_iterate("# old title\n\nSome old text\n")
+            .filter_map(|(node, _)| match node {
+                Node::Text(ref text) => Some(text.get_content().unwrap()),
+                _ => None,
+            })
+            .collect();
+
+        assert_eq!(texts, vec!["new title", "Some new text"]);
+    }
+
+    #[test]
+    fn test_set_content_validated() {
+        let root = parse_document("Some `code`\n\n```\nblock\n```\n");
+        let mut nodes: Vec<Node> = root.iter().map(|(node, _)| node).collect();
+        let mut text = nodes.remove(2);
+        let mut code = nodes.remove(2);
+        let mut block = root.last_child().unwrap().unwrap();
+        let mut paragraph = root.first_child().unwrap().unwrap();
+
+        assert_eq!(text.set_content_validated("Other ").unwrap(), 1);
+        match text.set_content_validated("two\nlines") {
+            Err(DoogieError::InvalidContent(NodeType::CMarkNodeText)) => (),
+            _ => panic!("Expected an InvalidContent error"),
+        }
+        match code.set_content_validated("two\nlines") {
+            Err(DoogieError::InvalidContent(NodeType::CMarkNodeCode)) => (),
+            _ => panic!("Expected an InvalidContent error"),
+        }
+        match text.set_content_validated("carriage\rreturn") {
+            Err(DoogieError::InvalidContent(NodeType::CMarkNodeText)) => (),
+            _ => panic!("Expected an InvalidContent error"),
+        }
+        assert_eq!(block.set_content_validated("two\nlines\n").unwrap(), 1);
+        match paragraph.set_content_validated("text") {
+            Err(DoogieError::UnsupportedNodeType(NodeType::CMarkNodeParagraph)) => (),
+            _ => panic!("Expected an UnsupportedNodeType error"),
+        }
+        assert!(root.render_commonmark().starts_with("Other `code`\n"));
+    }
+
+    #[test]
+    fn test_sendable_document_across_threads() {
+        let sources = vec!["# One\n\nFirst *text*\n", "## Two\n\n- a\n- b\n"];
+
+        let workers: Vec<_> = sources
+            .iter()
+            .map(|source| {
+                let source = source.to_string();
+                thread::spawn(move || {
+                    let root = parse_document(&source);
+                    root.replace_text("First", "Only").unwrap();
+                    SendableDocument::from_node(&root)
+                })
+            })
+            .collect();
+        let documents: Vec<SendableDocument> =
+            workers.into_iter().map(|worker| worker.join().unwrap()).collect();
+
+        let first = documents[0].clone().into_node().unwrap();
+        let second = documents[1].clone().into_node().unwrap();
+
+        assert!(first.tree_eq(&parse_document("# One\n\nOnly *text*\n")).unwrap());
+        assert!(second.tree_eq(&parse_document(sources[1])).unwrap());
+        assert_eq!(documents[0].as_str(), first.render_commonmark());
+    }
+
+    #[test]
+    fn test_set_fence_info_checked() {
+        let root = parse_document("```\nlet x = 1;\n```\n");
+        let mut block = match root.first_child().unwrap().unwrap() {
+            Node::CodeBlock(block) => block,
+            _ => panic!("Expected a code block node"),
+        };
+
+        assert_eq!(block.set_fence_info_checked("rust").unwrap(), 1);
+        match block.set_fence_info_checked("rust\n") {
+            Err(DoogieError::InvalidFenceInfo('\n')) => (),
+            _ => panic!("Expected an InvalidFenceInfo error"),
+        }
+        match block.set_fence_info_checked("rust`") {
+            Err(DoogieError::InvalidFenceInfo('`')) => (),
+            _ => panic!("Expected an InvalidFenceInfo error"),
+        }
+        assert_eq!(block.get_fence_info().unwrap(), "rust");
+    }
+
+    #[test]
+    fn test_valid_child_types() {
+        let root = parse_document("# Title\n\n- item\n");
+        let heading = root.first_child().unwrap().unwrap();
+        let list = root.last_child().unwrap().unwrap();
+        let text = heading.first_child().unwrap().unwrap();
+
+        assert_eq!(root.valid_child_types().unwrap(), &*DOCUMENT_CHILDREN);
+        assert_eq!(heading.valid_child_types().unwrap(), &*HEADING_CHILDREN);
+        assert!(heading.valid_child_types().unwrap().contains(&NodeType::CMarkNodeEmph));
+
+        let list_types = list.valid_child_types().unwrap();
+        assert_eq!(list_types.len(), 1);
+        assert!(list_types.contains(&NodeType::CMarkNodeItem));
+
+        assert!(text.valid_child_types().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_large_documents() {
+        let mut source = String::new();
+        for i in 0..10_000 {
+            source.push_str(&format!("Paragraph *{}* with [a](link)\n\n", i));
+        }
+        let root = parse_document(&source);
+        assert_eq!(root.count_descendants(), 70_000);
+
+        let mut target = Node::from_type(NodeType::CMarkNodeDocument).unwrap();
+        target.manager().track_root(&target.pointer());
+        for mut paragraph in root.children().step_by(2) {
+            target.append_child(&mut paragraph).unwrap();
+        }
+        assert_eq!(root.children().count(), 5_000);
+        assert_eq!(target.children().count(), 5_000);
+        drop(root);
+        drop(target);
+
+        let deep = format!("{} deep\n", ">".repeat(1_000));
+        let root = parse_document(&deep);
+        assert_eq!(root.find_all(NodeType::CMarkNodeBlockQuote).unwrap().len(), 1_000);
+        assert!(root.render_html().contains("deep"));
+    }
+
+    #[test]
+    fn test_cached_node_type_matches_libcmark() {
+        let root = parse_document(
+            "# Title\n\n> Quote  \nwith *emph*, **strong**, `code`, <b>html</b>\n\n\
+             - [link](url)\n  ![image](src)\n\n```\ncode\n```\n\n<div>\n\n***\n",
+        );
+        let mut seen = HashSet::new();
+
+        for (node, _) in root.iter() {
+            let node_type = node.get_cmark_type().unwrap();
+            let raw_type = unsafe { cmark_node_get_type(node.pointer()) as u32 };
+
+            assert_eq!(node_type, NodeType::try_from(raw_type).unwrap());
+            seen.insert(node_type);
+        }
+
+        assert_eq!(seen.len(), 18);
+    }
+
+    #[test]
+    fn test_failed_append_leaves_child_in_place() {
+        let root = parse_document("Some text\n\n> > Inner\n");
+        let mut text = root.first_child().unwrap().unwrap().first_child().unwrap().unwrap();
+        let mut outer = root.last_child().unwrap().unwrap();
+        let mut inner = outer.first_child().unwrap().unwrap();
+        let mut paragraph = root.first_child().unwrap().unwrap();
+
+        match text.append_child(&mut paragraph) {
+            Err(DoogieError::InvalidChild { .. }) => (),
+            _ => panic!("Expected an InvalidChild error"),
+        }
+        match inner.append_child(&mut outer) {
+            Err(DoogieError::CyclicAppend) => (),
+            _ => panic!("Expected a CyclicAppend error"),
+        }
+        let mut same = outer.itself().unwrap();
+        match outer.append_child(&mut same) {
+            Err(DoogieError::CyclicAppend) => (),
+            _ => panic!("Expected a CyclicAppend error"),
+        }
+
+        assert_eq!(paragraph.parent().unwrap(), Some(root.itself().unwrap()));
+        assert_eq!(outer.parent().unwrap(), Some(root.itself().unwrap()));
+        assert!(!paragraph.manager().is_tracking(&paragraph.pointer()));
+        assert!(!outer.manager().is_tracking(&outer.pointer()));
+        assert_eq!(root.render_commonmark(), "Some text\n\n> > Inner\n");
+
+        paragraph.unlink();
+        assert!(text.append_child(&mut paragraph).is_err());
+        assert!(paragraph.parent().unwrap().is_none());
+        assert!(paragraph.manager().is_tracking(&paragraph.pointer()));
+        assert_eq!(paragraph.render_commonmark(), "Some text\n");
+
+        drop(root);
+        drop(paragraph);
+    }
+
+    #[test]
+    fn test_into_raw_and_from_raw_owned() {
+        let root = parse_document("# Title\n\nSome text\n");
+        let paragraph = root.last_child().unwrap().unwrap().detach();
+
+        let root_ptr = root.into_raw();
+        let paragraph_ptr = paragraph.into_raw();
+
+        let root = unsafe { Node::from_raw_owned(root_ptr).unwrap() };
+        let paragraph = unsafe { Node::from_raw_owned(paragraph_ptr).unwrap() };
+        assert!(root.manager().is_tracking(&root_ptr));
+        assert_eq!(root.render_commonmark(), "# Title\n");
+        assert_eq!(paragraph.render_commonmark(), "Some text\n");
+
+        match unsafe { Node::from_raw_owned(ptr::null_mut()) } {
+            Err(DoogieError::NodeNone) => (),
+            _ => panic!("Expected a NodeNone error"),
+        }
+    }
+
+    #[test]
+    fn test_custom_inline_on_enter_and_exit() {
+        let root = parse_document("Some text\n");
+        let mut text = root.first_child().unwrap().unwrap().first_child().unwrap().unwrap();
+        let mut custom = Node::from_type(NodeType::CMarkNodeCustomInline).unwrap();
+
+        if let Node::CustomInline(ref mut custom) = custom {
+            assert_eq!(custom.get_on_enter().unwrap(), "");
+            custom.set_on_enter(&String::from("<mark>")).unwrap();
+            custom.set_on_exit(&String::from("</mark>")).unwrap();
+            assert_eq!(custom.get_on_enter().unwrap(), "<mark>");
+            assert_eq!(custom.get_on_exit().unwrap(), "</mark>");
         }
+        text.insert_before(&mut custom).unwrap();
+        custom.append_child(&mut text).unwrap();
+
+        assert_eq!(root.render_html(), "<p><mark>Some text</mark></p>\n");
+
+        let copy = root.deep_copy().unwrap();
+        assert_eq!(copy.render_html(), "<p><mark>Some text</mark></p>\n");
     }
 
     proptest! {
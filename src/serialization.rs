@@ -0,0 +1,232 @@
+//! Serde support for the `Node` tree, enabled by the `serde` feature
+//!
+//! A `Node` serializes as a recursive structure holding its type string, its source position, the
+//! attributes specific to its type and its children in document order. Deserializing rebuilds an
+//! equivalent tree without parsing; source positions cannot be restored and are ignored.
+
+use super::{DoogieResult, Node, NodeResource};
+use constants::*;
+use errors::DoogieError;
+use serde::de::Error as DeError;
+use serde::ser::Error as SerError;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use try_from::TryFrom;
+
+/// Owned representation of a `Node` and its subtree in serialized form
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct SerializedNode {
+    /// The libcmark type string of the node, e.g. `block_quote`
+    #[serde(rename = "type")]
+    pub node_type: String,
+    #[serde(default)]
+    pub start_line: u32,
+    #[serde(default)]
+    pub start_column: u32,
+    #[serde(default)]
+    pub end_line: u32,
+    #[serde(default)]
+    pub end_column: u32,
+    /// Textual content of `Text`, `Code`, `CodeBlock`, `HtmlBlock` and `HtmlInline` nodes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub literal: Option<String>,
+    /// Info text of `CodeBlock` nodes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub info: Option<String>,
+    /// Destination of `Link` and `Image` nodes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Title of `Link` and `Image` nodes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// Level of `Heading` nodes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub level: Option<u32>,
+    /// Either `bullet` or `ordered` for `List` nodes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub list_type: Option<String>,
+    /// Either `period` or `paren` for ordered `List` nodes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub list_delim: Option<String>,
+    /// Starting number of `List` nodes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub list_start: Option<u32>,
+    /// Tightness of `List` nodes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub list_tight: Option<bool>,
+    #[serde(default)]
+    pub children: Vec<SerializedNode>,
+}
+
+impl SerializedNode {
+    /// Captures the subtree rooted at the given `Node`
+    pub fn from_node(node: &Node) -> DoogieResult<Self> {
+        let mut serialized = SerializedNode {
+            node_type: node.get_cmark_type()?.to_string(),
+            start_line: node.get_start_line(),
+            start_column: node.get_start_column(),
+            end_line: node.get_end_line(),
+            end_column: node.get_end_column(),
+            literal: None,
+            info: None,
+            url: None,
+            title: None,
+            level: None,
+            list_type: None,
+            list_delim: None,
+            list_start: None,
+            list_tight: None,
+            children: Vec::new(),
+        };
+
+        match *node {
+            Node::Text(ref text) => serialized.literal = Some(text.get_content()?),
+            Node::Code(ref code) => serialized.literal = Some(code.get_content()?),
+            Node::HtmlBlock(ref html) => serialized.literal = Some(html.get_content()?),
+            Node::HtmlInline(ref html) => serialized.literal = Some(html.get_content()?),
+            Node::CodeBlock(ref block) => {
+                serialized.literal = Some(block.get_content()?);
+                serialized.info = Some(block.get_fence_info()?);
+            }
+            Node::Heading(ref heading) => serialized.level = Some(heading.get_level() as u32),
+            Node::List(ref list) => {
+                let list_type = match list.get_list_type()? {
+                    ListType::CMarkOrderedList => "ordered",
+                    _ => "bullet",
+                };
+                let list_delim = match list.get_delim_type()? {
+                    DelimType::CMarkParenDelim => "paren",
+                    _ => "period",
+                };
+                serialized.list_type = Some(list_type.to_string());
+                serialized.list_delim = Some(list_delim.to_string());
+                serialized.list_start = Some(list.get_start());
+                serialized.list_tight = Some(list.is_tight());
+            }
+            Node::Link(ref link) => {
+                serialized.url = Some(link.get_url()?);
+                serialized.title = Some(link.get_title()?);
+            }
+            Node::Image(ref image) => {
+                serialized.url = Some(image.get_url()?);
+                serialized.title = Some(image.get_title()?);
+            }
+            _ => (),
+        }
+
+        let mut child = node.first_child()?;
+        while let Some(current) = child {
+            serialized.children.push(SerializedNode::from_node(&current)?);
+            child = current.next_sibling()?;
+        }
+
+        Ok(serialized)
+    }
+
+    /// Builds a new tree of `Node`s from the serialized subtree
+    ///
+    /// The root of the returned tree is owned by its own `ResourceManager`. Each `Node` is tracked
+    /// as soon as it is created, so the part of the tree already built is freed if an error occurs.
+    /// Unrecognized `list_type` and `list_delim` values are returned as
+    /// `DoogieError::UnknownListAttribute`.
+    pub fn to_node(&self) -> DoogieResult<Node> {
+        let mut node = Node::from_type(NodeType::try_from(self.node_type.as_str())?)?;
+        node.manager().track_root(&node.pointer());
+
+        match node {
+            Node::Text(ref mut text) => {
+                if let Some(ref literal) = self.literal {
+                    text.set_content(literal)?;
+                }
+            }
+            Node::Code(ref mut code) => {
+                if let Some(ref literal) = self.literal {
+                    code.set_content(literal)?;
+                }
+            }
+            Node::HtmlBlock(ref mut html) => {
+                if let Some(ref literal) = self.literal {
+                    html.set_content(literal)?;
+                }
+            }
+            Node::HtmlInline(ref mut html) => {
+                if let Some(ref literal) = self.literal {
+                    html.set_content(literal)?;
+                }
+            }
+            Node::CodeBlock(ref mut block) => {
+                if let Some(ref literal) = self.literal {
+                    block.set_content(literal)?;
+                }
+                if let Some(ref info) = self.info {
+                    block.set_fence_info(info)?;
+                }
+            }
+            Node::Heading(ref mut heading) => {
+                if let Some(level) = self.level {
+                    heading.set_level(level)?;
+                }
+            }
+            Node::List(ref mut list) => {
+                if let Some(ref list_type) = self.list_type {
+                    list.set_list_type(match list_type.as_str() {
+                        "bullet" => ListType::CMarkBulletList,
+                        "ordered" => ListType::CMarkOrderedList,
+                        other => return Err(DoogieError::UnknownListAttribute(other.to_string())),
+                    })?;
+                }
+                if let Some(ref list_delim) = self.list_delim {
+                    list.set_delim_type(match list_delim.as_str() {
+                        "period" => DelimType::CMarkPeriodDelim,
+                        "paren" => DelimType::CMarkParenDelim,
+                        other => return Err(DoogieError::UnknownListAttribute(other.to_string())),
+                    })?;
+                }
+                if let Some(start) = self.list_start {
+                    list.set_start(start)?;
+                }
+                if let Some(tight) = self.list_tight {
+                    list.set_tight(tight)?;
+                }
+            }
+            Node::Link(ref mut link) => {
+                if let Some(ref url) = self.url {
+                    link.set_url(url)?;
+                }
+                if let Some(ref title) = self.title {
+                    link.set_title(title)?;
+                }
+            }
+            Node::Image(ref mut image) => {
+                if let Some(ref url) = self.url {
+                    image.set_url(url)?;
+                }
+                if let Some(ref title) = self.title {
+                    image.set_title(title)?;
+                }
+            }
+            _ => (),
+        }
+
+        for child in &self.children {
+            node.append_child(&mut child.to_node()?)?;
+        }
+
+        Ok(node)
+    }
+}
+
+impl Serialize for Node {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        SerializedNode::from_node(self)
+            .map_err(S::Error::custom)?
+            .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Node {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        SerializedNode::deserialize(deserializer)?
+            .to_node()
+            .map_err(D::Error::custom)
+    }
+}